@@ -58,7 +58,9 @@ pub trait Loadable {
 
 impl<A: Asset> Loadable for AssetRef<A> {
     fn load(&mut self, load_context: &mut bevy::asset::LoadContext) {
-        *self = Self::Handle(load_context.load(self.get_path().unwrap()));
+        if let Self::Path(path) = self {
+            *self = Self::Handle(load_context.load(path.clone()));
+        }
     }
 }
 
@@ -18,8 +18,10 @@ use bevy_xpbd_3d::plugins::{PhysicsDebugPlugin, PhysicsPlugins};
 use debugging::InspectorPlugin;
 use plugins::assets::AssetsLoadingPlugin;
 
+use plugins::enemy::EnemyPlugin;
 use plugins::environment;
 use plugins::gen::GenPlugins;
+use plugins::hotbar::HotbarPlugin;
 // use plugins::cursor::CursorPlugin;
 use plugins::network::NetworkPlugin;
 use plugins::{camera::CameraPlugin, crafting::CraftingPlugin, player::PlayerPlugin};
@@ -68,6 +70,8 @@ fn main() {
             GenPlugins,
             PlayerPlugin,
             CameraPlugin,
+            HotbarPlugin,
+            EnemyPlugin,
             CraftingPlugin,
             AssetsLoadingPlugin,
             NetworkPlugin,
@@ -90,6 +94,8 @@ fn init_loaders(mut contexts: EguiContexts) {
 enum GameState {
     #[default]
     Loading,
+    /// Loaded assets are checked, the game doesn't continue if they have mistakes.
+    Validation,
     Menu,
     Game,
 }
@@ -1,12 +1,35 @@
-use bevy::app::Plugin;
+use bevy::{
+    app::{Plugin, Update},
+    asset::{AssetServer, Assets},
+    ecs::{
+        schedule::{common_conditions::resource_exists, IntoSystemConfigs, NextState, OnEnter},
+        system::{Commands, Res, ResMut, Resource},
+    },
+    log::{error, info},
+};
+#[cfg(feature = "hot_reload")]
+use bevy::{
+    asset::AssetEvent,
+    ecs::schedule::{
+        common_conditions::{in_state, on_event},
+        Condition,
+    },
+};
 use bevy_asset_loader::loading_state::{
     config::ConfigureLoadingState, LoadingState, LoadingStateAppExt,
 };
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
 
 use crate::GameState;
 
 use super::{
-    crafting::{ItemsCollection, WorkbenchesCollection},
+    crafting::{
+        logic::{
+            validate_assets, AssetError, EnchantmentDefinition, Item, LootTable,
+            LootTablesCollection, Workbench,
+        },
+        EnchantmentsCollection, ItemsCollection, WorkbenchesCollection,
+    },
     player::PlayerCollection,
 };
 
@@ -16,10 +39,87 @@ impl Plugin for AssetsLoadingPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_loading_state(
             LoadingState::new(GameState::Loading)
-                .continue_to_state(GameState::Menu)
+                .continue_to_state(GameState::Validation)
+                .on_failure_continue_to_state(GameState::Validation)
                 .load_collection::<PlayerCollection>() // .load_collection::<CursorFolderCollection>(),
                 .load_collection::<WorkbenchesCollection>()
-                .load_collection::<ItemsCollection>(),
+                .load_collection::<ItemsCollection>()
+                .load_collection::<EnchantmentsCollection>()
+                .load_collection::<LootTablesCollection>(),
+        )
+        .add_systems(OnEnter(GameState::Validation), validate_loaded_assets)
+        .add_systems(
+            Update,
+            show_asset_errors.run_if(resource_exists::<AssetErrors>),
+        );
+
+        // Saved fixes are picked up by the file watcher, so the assets are checked again
+        #[cfg(feature = "hot_reload")]
+        app.add_systems(
+            Update,
+            validate_loaded_assets.run_if(
+                in_state(GameState::Validation).and_then(
+                    on_event::<AssetEvent<Item>>()
+                        .or_else(on_event::<AssetEvent<Workbench>>())
+                        .or_else(on_event::<AssetEvent<EnchantmentDefinition>>())
+                        .or_else(on_event::<AssetEvent<LootTable>>()),
+                ),
+            ),
         );
     }
 }
+
+/// Mistakes in the loaded assets, the game stays in [`GameState::Validation`] until they are fixed.
+/// With the `hot_reload` feature they are checked again whenever an asset changes.
+#[derive(Resource, Debug)]
+pub struct AssetErrors(pub Vec<AssetError>);
+
+fn validate_loaded_assets(
+    mut commands: Commands,
+    mut game_state: ResMut<NextState<GameState>>,
+    asset_server: Res<AssetServer>,
+    item_assets: Res<Assets<Item>>,
+    workbench_assets: Res<Assets<Workbench>>,
+    enchantment_assets: Res<Assets<EnchantmentDefinition>>,
+    table_assets: Res<Assets<LootTable>>,
+    items: Option<Res<ItemsCollection>>,
+    workbenches: Option<Res<WorkbenchesCollection>>,
+    enchantments: Option<Res<EnchantmentsCollection>>,
+    loot_tables: Option<Res<LootTablesCollection>>,
+) {
+    let mut errors = validate_assets(
+        &item_assets,
+        workbench_assets.iter().map(|(_, workbench)| workbench),
+        enchantment_assets.iter().map(|(_, definition)| definition),
+        &table_assets,
+        |id| asset_server.get_path(id).map(|path| path.to_string()),
+    );
+    if items.is_none() || workbenches.is_none() || enchantments.is_none() || loot_tables.is_none() {
+        errors.insert(0, AssetError::LoadingFailed);
+    }
+
+    if errors.is_empty() {
+        info!("Assets are valid");
+        commands.remove_resource::<AssetErrors>();
+        game_state.set(GameState::Menu);
+        return;
+    }
+    for err in &errors {
+        error!("Invalid assets: {err}");
+    }
+    commands.insert_resource(AssetErrors(errors));
+}
+
+fn show_asset_errors(mut contexts: EguiContexts, errors: Res<AssetErrors>) {
+    egui::Window::new("Invalid assets")
+        .resizable(true)
+        .show(contexts.ctx_mut(), |ui| {
+            #[cfg(feature = "hot_reload")]
+            ui.label("Fix the assets, they are checked again once saved:");
+            #[cfg(not(feature = "hot_reload"))]
+            ui.label("Fix the assets and restart the game:");
+            for err in &errors.0 {
+                ui.label(err.to_string());
+            }
+        });
+}
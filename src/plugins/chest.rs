@@ -1,19 +1,24 @@
 use bevy::{
-    app::{Plugin, Startup, Update},
+    app::{Plugin, PreUpdate, Update},
+    asset::{Assets, Handle},
     core::Name,
     ecs::{
         change_detection::DetectChangesMut,
         component::Component,
         entity::Entity,
-        event::{Event, EventReader},
-        query::{With, Without},
-        schedule::{common_conditions::in_state, IntoSystemConfigs},
-        system::{Commands, Query, ResMut, Resource},
+        event::{Event, EventReader, EventWriter},
+        query::{Added, With, Without},
+        schedule::{
+            common_conditions::{in_state, resource_exists},
+            IntoSystemConfigs, OnEnter,
+        },
+        system::{Commands, Local, Query, Res, ResMut, Resource},
     },
-    math::Vec2,
+    log::warn,
+    math::{Vec2, Vec3},
     reflect::{std_traits::ReflectDefault, Reflect},
-    render::color::Color,
-    sprite::{Sprite, SpriteBundle},
+    render::{color::Color, texture::Image, view::VisibilityBundle},
+    sprite::Sprite,
     transform::components::{GlobalTransform, Transform},
 };
 use bevy_inspector_egui::{
@@ -25,15 +30,25 @@ use bevy_mod_picking::{
     prelude::{ListenerInput, On},
     PickableBundle,
 };
+use bevy_replicon::{
+    client::ClientSet,
+    core::replication_rules::{AppReplicationExt, Replication},
+};
+use bevy_replicon_renet::renet::RenetServer;
+use serde::{Deserialize, Serialize};
 
-use crate::{item, item_kind, utils::squared_distance, GameState};
+use crate::{utils::squared_distance, GameState};
 
 use super::{
     crafting::{
-        logic::{Inventory, Item, ItemStack},
-        show_item,
+        logic::{
+            InstanceData, Inventory, Item, ItemDefinitions, ItemId, ItemStack, LootContext,
+            LootSource, LootTable, LootTablesCollection, TransferItem,
+        },
+        show_item, show_search,
     },
-    player::Player,
+    gen::noises::NoiseConfig,
+    player::{LocalPLayer, INTERACTION_DISTANCE_SQUARED},
 };
 
 pub struct ChestPlugin;
@@ -41,10 +56,15 @@ pub struct ChestPlugin;
 impl Plugin for ChestPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_event::<CheckChest>()
+            .replicate::<Chest>()
             .register_type::<ChestWindowState>()
             .init_resource::<MoveStack>()
             .add_plugins(ResourceInspectorPlugin::<ChestWindowState>::default())
-            .add_systems(Startup, spawn_chest)
+            .add_systems(
+                OnEnter(GameState::Game),
+                spawn_chest.run_if(resource_exists::<RenetServer>),
+            )
+            .add_systems(PreUpdate, chest_init_system.after(ClientSet::Receive))
             .add_systems(
                 Update,
                 (
@@ -76,63 +96,94 @@ impl From<ListenerInput<Pointer<Click>>> for CheckChest {
     }
 }
 
-#[derive(Component, Debug, Reflect, Default)]
+#[derive(Component, Debug, Reflect, Default, Serialize, Deserialize)]
 #[reflect(Default)]
 pub struct Chest;
 
-fn spawn_chest(mut commands: Commands, mut items_query: Query<(&mut Item, &mut ItemStack)>) {
-    let mut inventory = Inventory::new();
-    let item = item! { "ExampleItem1", item_kind!(primitive), amount = 1, level = 1 };
-    inventory.add_combine(&mut commands, &mut items_query, vec![item.as_tuple()]);
-    commands
-        .spawn(Chest)
-        .insert(inventory)
-        .insert(Name::new("Chest"))
-        .insert(SpriteBundle {
-            sprite: Sprite {
-                custom_size: Some(Vec2::new(10.0, 10.0)),
-                color: Color::BLUE,
-                ..Default::default()
-            },
-            transform: Transform::from_xyz(10.0, 10.0, 10.0),
-            ..Default::default()
-        })
-        .insert(PickableBundle::default())
-        .insert(On::<Pointer<Click>>::send_event::<CheckChest>());
+/// Loot table of the spawned chests.
+const CHEST_LOOT: &str = "loot_tables/chest.loot.ron";
 
-    let mut inventory = Inventory::new();
-    let item = item! { "ExampleItem2", item_kind!(primitive), amount = 1, level = 1 };
-    inventory.add_combine(&mut commands, &mut items_query, vec![item.as_tuple()]);
-    commands
-        .spawn(Chest)
-        .insert(inventory)
-        .insert(Name::new("Chest"))
-        .insert(SpriteBundle {
-            sprite: Sprite {
+/// Places the chests when the server enters the game, clients get them through replication.
+/// They are filled from the [`CHEST_LOOT`] table, the seed of a chest comes from
+/// the world seed and its position.
+fn spawn_chest(
+    mut commands: Commands,
+    noise_config: Res<NoiseConfig>,
+    mut items_query: Query<(&ItemId, &mut ItemStack, InstanceData)>,
+    loot_tables: Res<LootTablesCollection>,
+    table_assets: Res<Assets<LootTable>>,
+    item_assets: Res<Assets<Item>>,
+) {
+    let table = loot_tables
+        .get(CHEST_LOOT)
+        .and_then(|handle| table_assets.get(handle));
+    if table.is_none() {
+        warn!("Loot table {CHEST_LOOT:?} is not loaded, chests are empty");
+    }
+    let context = LootContext {
+        source: LootSource::Chest,
+        level: 1,
+    };
+
+    for translation in [Vec3::new(10.0, 10.0, 10.0), Vec3::new(50.0, 10.0, 10.0)] {
+        let mut inventory = Inventory::new();
+        if let Some(table) = table {
+            let seed = LootTable::position_seed(noise_config.seed(), translation);
+            let loot = table.roll_items(seed, &context, &table_assets, &item_assets);
+            inventory.add_combine(
+                &mut commands,
+                &mut items_query,
+                loot.iter().map(|(item, stack)| (*item, stack)).collect(),
+            );
+        }
+        commands.spawn((
+            Chest,
+            inventory,
+            Transform::from_translation(translation),
+            Replication,
+        ));
+    }
+}
+
+fn chest_init_system(mut commands: Commands, spawned_chests: Query<Entity, Added<Chest>>) {
+    for entity in &spawned_chests {
+        commands.entity(entity).insert((
+            Name::new("Chest"),
+            Sprite {
                 custom_size: Some(Vec2::new(10.0, 10.0)),
                 color: Color::BLUE,
                 ..Default::default()
             },
-            transform: Transform::from_xyz(50.0, 10.0, 10.0),
-            ..Default::default()
-        })
-        .insert(PickableBundle::default())
-        .insert(On::<Pointer<Click>>::send_event::<CheckChest>());
+            Handle::<Image>::default(),
+            GlobalTransform::default(),
+            VisibilityBundle::default(),
+            PickableBundle::default(),
+            On::<Pointer<Click>>::send_event::<CheckChest>(),
+        ));
+    }
 }
 
 fn check_chest(
     mut check_event: EventReader<CheckChest>,
-    player_query: Query<(&GlobalTransform, Entity), With<Player>>,
-    mut chest_query: Query<(&mut Sprite, &GlobalTransform, Entity), (With<Chest>, Without<Player>)>,
+    player_query: Query<(&GlobalTransform, Entity), With<LocalPLayer>>,
+    mut chest_query: Query<
+        (&mut Sprite, &GlobalTransform, Entity),
+        (With<Chest>, Without<LocalPLayer>),
+    >,
     mut commands: Commands,
 ) {
-    let (player_transform, player_entity) = player_query.get_single().unwrap();
+    let Ok((player_transform, player_entity)) = player_query.get_single() else {
+        return;
+    };
     for chest in check_event.read() {
-        let (mut sprite, chest_transform, chest_entity) = chest_query.get_mut(chest.chest).unwrap();
+        let Ok((mut sprite, chest_transform, chest_entity)) = chest_query.get_mut(chest.chest)
+        else {
+            continue;
+        };
         if squared_distance(
             player_transform.translation(),
             chest_transform.translation(),
-        ) <= 500.0
+        ) <= INTERACTION_DISTANCE_SQUARED
         {
             commands.insert_resource(ChestWindowState {
                 left_inventory: player_entity,
@@ -157,94 +208,65 @@ impl Default for MoveStack {
 }
 
 fn handle_chest_inventory_window(
-    mut commands: Commands,
     mut contexts: EguiContexts,
     chest_state: Option<ResMut<ChestWindowState>>,
-    mut player_inventory: Query<&mut Inventory, With<Player>>,
-    mut chest_query: Query<&mut Inventory, (With<Chest>, Without<Player>)>,
-    mut items_query: Query<(&mut Item, &mut ItemStack)>,
+    inventories: Query<&Inventory>,
+    items_query: Query<(&ItemId, &ItemStack)>,
+    definitions: ItemDefinitions,
     mut move_stack: ResMut<MoveStack>,
+    mut search: Local<String>,
+    mut transfer_events: EventWriter<TransferItem>,
 ) {
     if let Some(mut chest_state) = chest_state {
         let ChestWindowState {
             left_inventory,
             right_inventory,
             ..
-        } = chest_state.as_mut();
-        let mut chest_inventory = chest_query.get_mut(*right_inventory).unwrap();
-        let mut player_inventory = player_inventory.get_mut(*left_inventory).unwrap();
+        } = *chest_state;
+        let Ok([player_inventory, chest_inventory]) =
+            inventories.get_many([left_inventory, right_inventory])
+        else {
+            return;
+        };
 
         egui::Window::new("Chest Inventory")
             .open(&mut chest_state.is_open)
             .show(contexts.ctx_mut(), |ui| {
+                show_search(&mut search, ui);
                 ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
-                    ui.vertical(|ui| {
-                        for entity in player_inventory.map.clone().into_iter().flatten() {
-                            if let Ok((item, mut stack)) = items_query.get_mut(entity) {
-                                if stack.0 == 0 {
+                    for (inventory, from, to, label) in [
+                        (player_inventory, left_inventory, right_inventory, "->"),
+                        (chest_inventory, right_inventory, left_inventory, "<-"),
+                    ] {
+                        ui.vertical(|ui| {
+                            for (slot, entity) in inventory
+                                .map
+                                .iter()
+                                .enumerate()
+                                .filter_map(|(slot, opt)| opt.map(|entity| (slot, entity)))
+                            {
+                                let Some((item, stack)) = items_query
+                                    .get(entity)
+                                    .ok()
+                                    .and_then(|(id, stack)| Some((definitions.get(id)?, stack)))
+                                    .filter(|(item, _)| item.matches_search(&search))
+                                else {
                                     continue;
-                                }
+                                };
 
-                                show_item((&item, &stack), ui, true);
+                                show_item((item, stack), ui, true);
 
                                 let max = stack.0;
 
-                                let move_button = ui.button("->");
-
-                                if move_button.clicked()
-                                    && player_inventory.take_linear(entity).is_some()
-                                {
-                                    stack.0 -= move_stack.0;
-                                    player_inventory.add_single(entity);
-
-                                    let it = &item.clone();
-                                    let stack = &ItemStack(move_stack.0);
-                                    let items = vec![(it, stack)];
-
-                                    chest_inventory.add_combine(
-                                        &mut commands,
-                                        &mut items_query,
-                                        items,
-                                    );
-
-                                    move_stack.set_if_neq(MoveStack::default());
-                                }
-
-                                move_button.context_menu(|ui| {
-                                    ui.label("Menu!");
-                                    ui.add(egui::Slider::new(&mut move_stack.as_mut().0, 1..=max));
-                                });
-                            }
-                        }
-                    });
-                    ui.vertical(|ui| {
-                        for entity in chest_inventory.map.clone().iter().filter_map(|x| *x) {
-                            if let Ok((item, mut stack)) = items_query.get_mut(entity) {
-                                if stack.0 == 0 {
-                                    continue;
-                                }
-
-                                show_item((&item, &stack), ui, true);
-
-                                let max = stack.0;
-
-                                let move_button = ui.button("<-");
-
-                                if move_button.clicked()
-                                    && chest_inventory.take_linear(entity).is_some()
-                                {
-                                    stack.0 -= move_stack.0;
-                                    chest_inventory.add_single(entity);
-
-                                    let it = &item.clone();
-                                    let stack = &ItemStack(move_stack.0);
-                                    let items = vec![(it, stack)];
+                                let move_button = ui.button(label);
 
-                                    player_inventory.add_combine(
-                                        &mut commands,
-                                        &mut items_query,
-                                        items,
-                                    );
+                                if move_button.clicked() {
+                                    transfer_events.send(TransferItem {
+                                        from,
+                                        to,
+                                        slot,
+                                        amount: move_stack.0.min(max),
+                                    });
 
                                     move_stack.set_if_neq(MoveStack::default());
                                 }
@@ -254,8 +276,8 @@ fn handle_chest_inventory_window(
                                     ui.add(egui::Slider::new(&mut move_stack.as_mut().0, 1..=max));
                                 });
                             }
-                        }
-                    });
+                        });
+                    }
                 });
             });
     }
@@ -0,0 +1,226 @@
+use bevy::{
+    app::{Plugin, PreUpdate, Update},
+    asset::Assets,
+    core::Name,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{EventReader, EventWriter},
+        query::{Added, With, Without},
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Commands, Query, Res, ResMut},
+    },
+    hierarchy::{BuildChildren, Parent},
+    input::{keyboard::KeyCode, ButtonInput},
+    log::warn,
+    math::{primitives::Cuboid, Vec3},
+    pbr::StandardMaterial,
+    render::{color::Color, mesh::Mesh, view::VisibilityBundle},
+    time::Time,
+    transform::components::{GlobalTransform, Transform},
+};
+use bevy_replicon::{
+    client::ClientSet, core::common_conditions::has_authority,
+    network_event::client_event::FromClient,
+};
+use bevy_xpbd_3d::{
+    components::RigidBody,
+    plugins::collision::{Collider, CollidingEntities, Sensor},
+};
+
+use crate::{
+    plugins::player::{LocalPLayer, Player, SelectedSlot},
+    GameState,
+};
+
+use super::logic::{
+    DropError, DropItem, DroppedItem, InstanceData, Inventory, ItemDefinitions, ItemId, ItemStack,
+    PickupDelay,
+};
+
+pub struct DroppedItemsPlugin;
+
+impl Plugin for DroppedItemsPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(
+            PreUpdate,
+            (
+                dropped_item_init_system,
+                dropped_item_physics.run_if(has_authority),
+            )
+                .after(ClientSet::Receive),
+        )
+        .add_systems(
+            Update,
+            (
+                drop_input,
+                (drop_item, tick_pickup_delay, pickup_dropped_items)
+                    .chain()
+                    .run_if(has_authority),
+            )
+                .run_if(in_state(GameState::Game)),
+        );
+    }
+}
+
+/// Half of the size of the dropped item's cube.
+const DROPPED_ITEM_SIZE: f32 = 0.25;
+
+/// Players closer than this to a dropped item pick it up.
+const PICKUP_RADIUS: f32 = 1.0;
+
+/// Sensor around a dropped item that detects players.
+#[derive(Component)]
+struct PickupSensor;
+
+/// Drops one item from the selected hotbar slot, the whole stack with shift.
+fn drop_input(
+    mut drop_events: EventWriter<DropItem>,
+    input: Res<ButtonInput<KeyCode>>,
+    player: Query<&SelectedSlot, With<LocalPLayer>>,
+) {
+    let Ok(selected) = player.get_single() else {
+        return;
+    };
+    if input.just_pressed(KeyCode::KeyQ) {
+        let amount = if input.pressed(KeyCode::ShiftLeft) {
+            u8::MAX
+        } else {
+            1
+        };
+        drop_events.send(DropItem {
+            slot: selected.0,
+            amount,
+        });
+    }
+}
+
+/// Takes the items out of the player's inventory and puts them in front of the player.
+fn drop_item(
+    mut commands: Commands,
+    mut drop_events: EventReader<FromClient<DropItem>>,
+    mut players: Query<(&Player, &mut Inventory, &GlobalTransform)>,
+    mut items_query: Query<(&ItemId, &mut ItemStack, InstanceData)>,
+) {
+    for FromClient { client_id, event } in drop_events.read() {
+        let result = players
+            .iter_mut()
+            .find(|(player, ..)| player.0 == *client_id)
+            .ok_or(DropError::NoPlayer)
+            .and_then(|(_, mut inventory, player_transform)| {
+                let entity = inventory
+                    .take_amount(&mut commands, &mut items_query, event.slot, event.amount)
+                    .ok_or(DropError::EmptySlot)?;
+                let translation =
+                    player_transform.translation() + player_transform.forward() * 1.5 + Vec3::Y;
+                commands.entity(entity).insert((
+                    DroppedItem,
+                    PickupDelay::default(),
+                    Transform::from_translation(translation),
+                ));
+                Ok(())
+            });
+
+        if let Err(err) = result {
+            warn!("{client_id:?} failed to drop {event:?}: {err:?}");
+        }
+    }
+}
+
+/// Adds the mesh of dropped items on the server and clients.
+fn dropped_item_init_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut standard_materials: ResMut<Assets<StandardMaterial>>,
+    dropped_items: Query<Entity, Added<DroppedItem>>,
+) {
+    for entity in &dropped_items {
+        commands.entity(entity).insert((
+            Name::new("Dropped item"),
+            meshes.add(Cuboid::from_size(Vec3::splat(DROPPED_ITEM_SIZE * 2.0))),
+            standard_materials.add(StandardMaterial {
+                base_color: Color::ORANGE,
+                ..Default::default()
+            }),
+            GlobalTransform::default(),
+            VisibilityBundle::default(),
+        ));
+    }
+}
+
+/// Dropped items are simulated by the server, clients get their transform.
+fn dropped_item_physics(mut commands: Commands, dropped_items: Query<Entity, Added<DroppedItem>>) {
+    for entity in &dropped_items {
+        commands
+            .entity(entity)
+            .insert((
+                RigidBody::Dynamic,
+                Collider::cuboid(
+                    DROPPED_ITEM_SIZE * 2.0,
+                    DROPPED_ITEM_SIZE * 2.0,
+                    DROPPED_ITEM_SIZE * 2.0,
+                ),
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    PickupSensor,
+                    Sensor,
+                    Collider::sphere(PICKUP_RADIUS),
+                    CollidingEntities::default(),
+                    Transform::default(),
+                    GlobalTransform::default(),
+                ));
+            });
+    }
+}
+
+fn tick_pickup_delay(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut delays: Query<(Entity, &mut PickupDelay)>,
+) {
+    for (entity, mut delay) in delays.iter_mut() {
+        if delay.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<PickupDelay>();
+        }
+    }
+}
+
+/// Moves dropped items into the inventories of the players touching them.
+/// Stacks are merged like in [`Inventory::add_instance`], what doesn't fit stays on the ground.
+fn pickup_dropped_items(
+    mut commands: Commands,
+    sensors: Query<(&Parent, &CollidingEntities), With<PickupSensor>>,
+    dropped_items: Query<(), (With<DroppedItem>, Without<PickupDelay>)>,
+    mut players: Query<&mut Inventory, With<Player>>,
+    mut items_query: Query<(&ItemId, &mut ItemStack, InstanceData)>,
+    definitions: ItemDefinitions,
+) {
+    for (parent, colliding) in sensors.iter() {
+        let dropped = parent.get();
+        if !dropped_items.contains(dropped) {
+            continue;
+        }
+        for player in colliding.iter() {
+            let Ok(mut inventory) = players.get_mut(*player) else {
+                continue;
+            };
+            let Some((item, amount, instance)) = items_query
+                .get(dropped)
+                .ok()
+                .filter(|(_, stack, _)| stack.0 > 0)
+                .and_then(|(id, stack, data)| {
+                    Some((definitions.get(id)?, stack.0, data.to_instance()))
+                })
+            else {
+                break;
+            };
+            let remaining =
+                inventory.add_instance(&mut commands, &mut items_query, item, amount, &instance);
+            // Empty stacks are despawned by `remove_empty_stacks`
+            if let Ok((_, mut stack, _)) = items_query.get_mut(dropped) {
+                stack.0 = remaining;
+            }
+        }
+    }
+}
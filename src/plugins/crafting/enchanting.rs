@@ -0,0 +1,349 @@
+use bevy::{
+    app::{Plugin, PreUpdate, Update},
+    asset::{Assets, Handle},
+    core::Name,
+    ecs::{
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        query::{Added, With, Without},
+        schedule::{
+            common_conditions::{in_state, resource_exists},
+            IntoSystemConfigs, OnEnter,
+        },
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    log::{info, warn},
+    math::Vec2,
+    render::{color::Color, texture::Image, view::VisibilityBundle},
+    sprite::Sprite,
+    transform::components::{GlobalTransform, Transform},
+};
+use bevy_asset_loader::asset_collection::AssetCollection;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+use bevy_mod_picking::{
+    events::{Click, Pointer},
+    prelude::{ListenerInput, On},
+    PickableBundle,
+};
+use bevy_replicon::{
+    client::ClientSet,
+    core::{common_conditions::has_authority, replication_rules::Replication},
+    network_event::client_event::FromClient,
+};
+use bevy_replicon_renet::renet::RenetServer;
+
+use crate::{
+    plugins::player::{LocalPLayer, Player, INTERACTION_DISTANCE_SQUARED},
+    utils::squared_distance,
+    GameState,
+};
+
+use super::{
+    logic::{
+        EnchantAction, EnchantError, EnchantItem, EnchantingTable, EnchantmentDefinition,
+        InstanceData, Inventory, Item, ItemDefinitions, ItemEnchantments, ItemId, ItemStack,
+    },
+    show_item,
+};
+
+pub struct EnchantingTablePlugin;
+
+impl Plugin for EnchantingTablePlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_event::<CheckEnchantingTable>()
+            .add_systems(
+                OnEnter(GameState::Game),
+                spawn_enchanting_table.run_if(resource_exists::<RenetServer>),
+            )
+            .add_systems(PreUpdate, table_init_system.after(ClientSet::Receive))
+            .add_systems(
+                Update,
+                (
+                    enchant_item.run_if(has_authority),
+                    check_enchanting_table,
+                    handle_enchanting_window.after(check_enchanting_table),
+                )
+                    .run_if(in_state(GameState::Game)),
+            );
+    }
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct EnchantmentsCollection {
+    #[asset(path = "enchantments", collection(typed))]
+    enchantments: Vec<Handle<EnchantmentDefinition>>,
+}
+
+impl EnchantmentsCollection {
+    /// Loaded enchantments offered by the tables.
+    pub fn definitions<'a>(
+        &'a self,
+        assets: &'a Assets<EnchantmentDefinition>,
+    ) -> impl Iterator<Item = &'a EnchantmentDefinition> {
+        self.enchantments
+            .iter()
+            .filter_map(|handle| assets.get(handle))
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct EnchantingWindowState {
+    pub is_open: bool,
+    pub table: Entity,
+}
+
+#[derive(Event, Debug)]
+struct CheckEnchantingTable {
+    pub table: Entity,
+}
+
+impl From<ListenerInput<Pointer<Click>>> for CheckEnchantingTable {
+    fn from(value: ListenerInput<Pointer<Click>>) -> Self {
+        Self {
+            table: value.target,
+        }
+    }
+}
+
+/// Places a single enchanting table between the chests.
+fn spawn_enchanting_table(mut commands: Commands) {
+    commands.spawn((
+        EnchantingTable,
+        Transform::from_xyz(20.0, 10.0, 10.0),
+        Replication,
+    ));
+}
+
+fn table_init_system(
+    mut commands: Commands,
+    spawned_tables: Query<Entity, Added<EnchantingTable>>,
+) {
+    for entity in &spawned_tables {
+        commands.entity(entity).insert((
+            Name::new("Enchanting table"),
+            Sprite {
+                custom_size: Some(Vec2::new(10.0, 10.0)),
+                color: Color::PURPLE,
+                ..Default::default()
+            },
+            Handle::<Image>::default(),
+            GlobalTransform::default(),
+            VisibilityBundle::default(),
+            PickableBundle::default(),
+            On::<Pointer<Click>>::send_event::<CheckEnchantingTable>(),
+        ));
+    }
+}
+
+fn check_enchanting_table(
+    mut commands: Commands,
+    mut check_event: EventReader<CheckEnchantingTable>,
+    player_query: Query<&GlobalTransform, With<LocalPLayer>>,
+    table_query: Query<&GlobalTransform, (With<EnchantingTable>, Without<LocalPLayer>)>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    for CheckEnchantingTable { table } in check_event.read() {
+        let Ok(table_transform) = table_query.get(*table) else {
+            continue;
+        };
+        if squared_distance(
+            player_transform.translation(),
+            table_transform.translation(),
+        ) <= INTERACTION_DISTANCE_SQUARED
+        {
+            commands.insert_resource(EnchantingWindowState {
+                is_open: true,
+                table: *table,
+            });
+        }
+    }
+}
+
+fn enchant_item(
+    mut commands: Commands,
+    mut enchant_events: EventReader<FromClient<EnchantItem>>,
+    mut players: Query<(&Player, &mut Inventory, &GlobalTransform)>,
+    tables: Query<&GlobalTransform, (With<EnchantingTable>, Without<Player>)>,
+    mut items_query: Query<(&ItemId, &mut ItemStack, InstanceData)>,
+    definitions: ItemDefinitions,
+    item_assets: Res<Assets<Item>>,
+    enchantments_collection: Res<EnchantmentsCollection>,
+    enchantment_assets: Res<Assets<EnchantmentDefinition>>,
+) {
+    for FromClient { client_id, event } in enchant_events.read() {
+        let result = players
+            .iter_mut()
+            .find(|(player, ..)| player.0 == *client_id)
+            .ok_or(EnchantError::NoPlayer)
+            .and_then(|(_, mut inventory, player_transform)| {
+                let table_transform = tables.get(event.table).ok();
+                if table_transform.is_none_or(|table_transform| {
+                    squared_distance(
+                        player_transform.translation(),
+                        table_transform.translation(),
+                    ) > INTERACTION_DISTANCE_SQUARED
+                }) {
+                    return Err(EnchantError::TooFar);
+                }
+                let item_entity = inventory.get(event.slot).ok_or(EnchantError::EmptySlot)?;
+                let (id, stack, data) = items_query
+                    .get(item_entity)
+                    .map_err(|_| EnchantError::EmptySlot)?;
+                let enchantable = definitions
+                    .get(id)
+                    .is_some_and(|item| item.kind.properties().is_some());
+                if !enchantable || stack.0 != 1 {
+                    return Err(EnchantError::NotEnchantable);
+                }
+                let mut enchantments = data.enchantments.cloned().unwrap_or_default();
+
+                match &event.action {
+                    EnchantAction::Apply(name) => {
+                        let definition = enchantments_collection
+                            .definitions(&enchantment_assets)
+                            .find(|definition| definition.enchantment.name == *name)
+                            .ok_or(EnchantError::UnknownEnchantment)?;
+                        if enchantments.get(name).is_some() {
+                            return Err(EnchantError::AlreadyEnchanted);
+                        }
+                        let cost = definition
+                            .cost
+                            .iter()
+                            .map(|(item, stack)| {
+                                Some((item_assets.get(item.get_handle()?)?, stack))
+                            })
+                            .collect::<Option<Vec<_>>>()
+                            .ok_or(EnchantError::UnresolvedCost)?;
+                        inventory
+                            .check_exchange(&items_query.to_readonly(), &cost, &[])
+                            .map_err(|_| EnchantError::MissingResources)?;
+                        for (item, stack) in cost {
+                            inventory.remove_item(&mut items_query, &item.id, stack.0);
+                        }
+                        enchantments.add(definition.enchantment.clone());
+                    }
+                    EnchantAction::Remove(name) => {
+                        enchantments
+                            .remove(name)
+                            .ok_or(EnchantError::NotEnchanted)?;
+                    }
+                }
+
+                if enchantments.0.is_empty() {
+                    commands.entity(item_entity).remove::<ItemEnchantments>();
+                } else {
+                    commands.entity(item_entity).insert(enchantments);
+                }
+                Ok(())
+            });
+
+        match result {
+            Ok(()) => info!("{client_id:?} enchanted an item: {:?}", event.action),
+            Err(err) => warn!("{client_id:?} failed to enchant an item: {err:?}"),
+        }
+    }
+}
+
+/// Enchantable items of the local player with their enchantments and the offered enchantments.
+fn handle_enchanting_window(
+    mut contexts: EguiContexts,
+    window_state: Option<ResMut<EnchantingWindowState>>,
+    player_query: Query<&Inventory, With<LocalPLayer>>,
+    items_query: Query<(&ItemId, &ItemStack, Option<&ItemEnchantments>)>,
+    definitions: ItemDefinitions,
+    enchantments_collection: Res<EnchantmentsCollection>,
+    enchantment_assets: Res<Assets<EnchantmentDefinition>>,
+    item_assets: Res<Assets<Item>>,
+    mut enchant_events: EventWriter<EnchantItem>,
+) {
+    let Some(mut window_state) = window_state else {
+        return;
+    };
+    let table = window_state.table;
+    let Ok(inventory) = player_query.get_single() else {
+        return;
+    };
+    let mut offers = enchantments_collection
+        .definitions(&enchantment_assets)
+        .collect::<Vec<_>>();
+    offers.sort_unstable_by(|a, b| a.enchantment.name.cmp(&b.enchantment.name));
+
+    egui::Window::new("Enchanting table")
+        .open(&mut window_state.is_open)
+        .resizable(true)
+        .show(contexts.ctx_mut(), |ui| {
+            for (slot, entity) in inventory
+                .map
+                .iter()
+                .enumerate()
+                .filter_map(|(slot, opt)| opt.map(|entity| (slot, entity)))
+            {
+                let Some((item, stack, enchantments)) = items_query
+                    .get(entity)
+                    .ok()
+                    .and_then(|(id, stack, enchantments)| {
+                        Some((definitions.get(id)?, stack, enchantments))
+                    })
+                    .filter(|(item, stack, _)| item.kind.properties().is_some() && stack.0 == 1)
+                else {
+                    continue;
+                };
+
+                show_item((item, stack), ui, true);
+                ui.horizontal(|ui| {
+                    for enchantment in enchantments.iter().flat_map(|e| &e.0) {
+                        let label = format!("{} {} ✖", enchantment.name, enchantment.level);
+                        if ui
+                            .button(label)
+                            .on_hover_text(format!("Remove: {:?}", enchantment.stats))
+                            .clicked()
+                        {
+                            enchant_events.send(EnchantItem {
+                                table,
+                                slot,
+                                action: EnchantAction::Remove(enchantment.name.clone()),
+                            });
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    for definition in offers.iter().filter(|definition| {
+                        enchantments
+                            .and_then(|e| e.get(&definition.enchantment.name))
+                            .is_none()
+                    }) {
+                        let cost = definition
+                            .cost
+                            .iter()
+                            .map(|(item, stack)| {
+                                let name = item
+                                    .get_handle()
+                                    .and_then(|handle| item_assets.get(handle))
+                                    .map_or("?", |item| &item.name);
+                                format!("{name} x{}", stack.0)
+                            })
+                            .collect::<Vec<_>>();
+                        let enchantment = &definition.enchantment;
+                        if ui
+                            .button(format!("+ {} {}", enchantment.name, enchantment.level))
+                            .on_hover_text(format!(
+                                "{:?}\nCost: {}",
+                                enchantment.stats,
+                                cost.join(", ")
+                            ))
+                            .clicked()
+                        {
+                            enchant_events.send(EnchantItem {
+                                table,
+                                slot,
+                                action: EnchantAction::Apply(enchantment.name.clone()),
+                            });
+                        }
+                    }
+                });
+                ui.separator();
+            }
+        });
+}
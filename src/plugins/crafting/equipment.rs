@@ -0,0 +1,171 @@
+use bevy::{
+    app::{Plugin, Update},
+    ecs::{
+        event::{EventReader, EventWriter},
+        query::With,
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Commands, Query, ResMut},
+    },
+    log::warn,
+    reflect::TypePath,
+};
+use bevy_inspector_egui::bevy_egui::EguiContexts;
+use bevy_replicon::{
+    core::common_conditions::has_authority, network_event::client_event::FromClient,
+};
+
+use crate::{
+    debugging::{show_window, InspectorWindowsAppExt},
+    plugins::player::{LocalPLayer, Player},
+    GameState, InspectorWindows,
+};
+
+use super::{
+    logic::{
+        EquipError, EquipItem, Equipment, EquipmentSlot, InstanceData, Inventory, ItemDefinitions,
+        ItemId, ItemStack, UnequipItem,
+    },
+    show_item,
+};
+
+pub struct EquipmentPlugin;
+
+impl Plugin for EquipmentPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.register_window::<EquipmentWindow>().add_systems(
+            Update,
+            (
+                (equip_item, unequip_item).chain().run_if(has_authority),
+                handle_equipment_window,
+            )
+                .run_if(in_state(GameState::Game)),
+        );
+    }
+}
+
+/// Moves an item from the inventory into the equipment, the previously equipped item
+/// takes its slot. Only one item of a stack is equipped, the rest is split off.
+fn equip_item(
+    mut commands: Commands,
+    mut equip_events: EventReader<FromClient<EquipItem>>,
+    mut players: Query<(&Player, &mut Inventory, &mut Equipment)>,
+    mut items_query: Query<(&ItemId, &mut ItemStack, InstanceData)>,
+    definitions: ItemDefinitions,
+) {
+    for FromClient { client_id, event } in equip_events.read() {
+        let result = players
+            .iter_mut()
+            .find(|(player, ..)| player.0 == *client_id)
+            .ok_or(EquipError::NoPlayer)
+            .and_then(|(_, mut inventory, mut equipment)| {
+                let entity = inventory.get(event.slot).ok_or(EquipError::EmptySlot)?;
+                let (id, stack, _) = items_query.get(entity).map_err(|_| EquipError::EmptySlot)?;
+                let amount = stack.0;
+                let fits = definitions.get(id).and_then(|item| item.equip) == Some(event.to.kind());
+                if !fits || !EquipmentSlot::all().any(|slot| slot == event.to) {
+                    return Err(EquipError::WrongSlot);
+                }
+
+                if amount > 1 {
+                    inventory
+                        .split_stack(&mut commands, &mut items_query, event.slot, amount - 1)
+                        .ok_or(EquipError::InventoryFull)?;
+                }
+                inventory.take(event.slot);
+                if let Ok(Some(previous)) = equipment.equip(event.to, entity) {
+                    inventory.insert_at(event.slot, previous);
+                }
+                Ok(())
+            });
+
+        if let Err(err) = result {
+            warn!("{client_id:?} failed to equip {event:?}: {err:?}");
+        }
+    }
+}
+
+fn unequip_item(
+    mut unequip_events: EventReader<FromClient<UnequipItem>>,
+    mut players: Query<(&Player, &mut Inventory, &mut Equipment)>,
+) {
+    for FromClient { client_id, event } in unequip_events.read() {
+        let result = players
+            .iter_mut()
+            .find(|(player, ..)| player.0 == *client_id)
+            .ok_or(EquipError::NoPlayer)
+            .and_then(|(_, mut inventory, mut equipment)| {
+                let entity = equipment.get(event.slot).ok_or(EquipError::EmptySlot)?;
+                inventory
+                    .add_single(entity)
+                    .ok_or(EquipError::InventoryFull)?;
+                equipment.unequip(event.slot);
+                Ok(())
+            });
+
+        if let Err(err) = result {
+            warn!("{client_id:?} failed to unequip {event:?}: {err:?}");
+        }
+    }
+}
+
+#[derive(TypePath)]
+enum EquipmentWindow {}
+
+/// Equipment of the local player and the inventory items that can be equipped.
+fn handle_equipment_window(
+    mut contexts: EguiContexts,
+    mut inspector_windows: ResMut<InspectorWindows>,
+    player_query: Query<(&Inventory, &Equipment), With<LocalPLayer>>,
+    items_query: Query<(&ItemId, &ItemStack)>,
+    definitions: ItemDefinitions,
+    mut equip_events: EventWriter<EquipItem>,
+    mut unequip_events: EventWriter<UnequipItem>,
+) {
+    let Ok((inventory, equipment)) = player_query.get_single() else {
+        return;
+    };
+    show_window::<EquipmentWindow, _>(inspector_windows.as_mut(), contexts.ctx_mut(), |ui| {
+        for slot in EquipmentSlot::all() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{slot:?}:"));
+                let Some((item, stack)) = equipment
+                    .get(slot)
+                    .and_then(|entity| items_query.get(entity).ok())
+                    .and_then(|(id, stack)| Some((definitions.get(id)?, stack)))
+                else {
+                    ui.label("-");
+                    return;
+                };
+                show_item((item, stack), ui, true);
+                if ui.button("Unequip").clicked() {
+                    unequip_events.send(UnequipItem { slot });
+                }
+            });
+        }
+
+        ui.separator();
+        for (slot, entity) in inventory
+            .map
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, opt)| opt.map(|entity| (slot, entity)))
+        {
+            let Some((item, stack, kind)) = items_query
+                .get(entity)
+                .ok()
+                .and_then(|(id, stack)| definitions.get(id).map(|item| (item, stack)))
+                .and_then(|(item, stack)| Some((item, stack, item.equip?)))
+            else {
+                continue;
+            };
+            ui.horizontal(|ui| {
+                show_item((item, stack), ui, true);
+                for to in EquipmentSlot::all().filter(|to| to.kind() == kind) {
+                    if ui.button(format!("Equip {to:?}")).clicked() {
+                        equip_events.send(EquipItem { slot, to });
+                    }
+                }
+            });
+        }
+    });
+}
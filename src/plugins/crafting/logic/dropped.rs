@@ -0,0 +1,67 @@
+use bevy::{
+    ecs::{component::Component, entity::Entity, event::Event, system::Commands},
+    math::Vec3,
+    reflect::{std_traits::ReflectDefault, Reflect},
+    time::{Timer, TimerMode},
+    transform::components::Transform,
+};
+use bevy_replicon::core::replication_rules::Replication;
+use serde::{Deserialize, Serialize};
+
+use super::{ItemId, ItemStack};
+
+/// An item entity lying in the world instead of an [`Inventory`](super::Inventory).
+/// Players pick it up by walking into it.
+#[derive(Component, Debug, Clone, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub struct DroppedItem;
+
+impl DroppedItem {
+    /// Spawns a new stack without instance data at the `translation`.
+    /// Unlike items dropped by players, it can be picked up right away.
+    pub fn spawn(
+        commands: &mut Commands,
+        id: &ItemId,
+        stack: ItemStack,
+        translation: Vec3,
+    ) -> Entity {
+        commands
+            .spawn((
+                id.clone(),
+                stack,
+                Replication,
+                DroppedItem,
+                Transform::from_translation(translation),
+            ))
+            .id()
+    }
+}
+
+/// Time until a dropped item can be picked up, so it's not picked up
+/// by the player who dropped it right away. Only exists on the server.
+#[derive(Component, Debug, Clone)]
+pub struct PickupDelay(pub Timer);
+
+impl PickupDelay {
+    pub const SECONDS: f32 = 1.5;
+}
+
+impl Default for PickupDelay {
+    fn default() -> Self {
+        Self(Timer::from_seconds(Self::SECONDS, TimerMode::Once))
+    }
+}
+
+/// Request to drop `amount` items from the `slot` of the player's inventory in front of the player.
+/// Asking for more than the stack holds drops the whole stack.
+#[derive(Debug, Clone, Event, Serialize, Deserialize)]
+pub struct DropItem {
+    pub slot: usize,
+    pub amount: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DropError {
+    NoPlayer,
+    EmptySlot,
+}
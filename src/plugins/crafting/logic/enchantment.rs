@@ -0,0 +1,179 @@
+use bevy::{
+    app::Plugin,
+    asset::{Asset, AssetApp, AsyncReadExt},
+    ecs::{
+        component::Component,
+        entity::{Entity, MapEntities},
+        event::Event,
+    },
+    reflect::{std_traits::ReflectDefault, Reflect},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::asset_macro::impl_asset_loader;
+
+use super::{ItemProperties, RecipeItems};
+
+pub struct EnchantmentPlugin;
+
+impl Plugin for EnchantmentPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_asset::<EnchantmentDefinition>()
+            .register_asset_loader(EnchantmentDefinitionAssetLoader)
+            .register_asset_reflect::<EnchantmentDefinition>();
+    }
+}
+
+/// Modifier of an item's stats. It only affects the player while the item is equipped.
+#[derive(Clone, PartialEq, Debug, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub struct Enchantment {
+    /// Enchantments are identified by their name, an item can't have the same one twice.
+    pub name: String,
+    #[serde(default = "Enchantment::default_level")]
+    pub level: u8,
+    /// Added to the stats of the enchanted item.
+    #[serde(default)]
+    pub stats: ItemProperties,
+}
+
+impl Enchantment {
+    fn default_level() -> u8 {
+        1
+    }
+}
+
+/// Enchantments of an item entity. Items with different enchantments don't stack.
+#[derive(Component, Clone, PartialEq, Debug, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub struct ItemEnchantments(pub Vec<Enchantment>);
+
+impl ItemEnchantments {
+    pub fn get(&self, name: &str) -> Option<&Enchantment> {
+        self.0.iter().find(|enchantment| enchantment.name == name)
+    }
+
+    /// Returns `false` if the item already has an enchantment with this name.
+    pub fn add(&mut self, enchantment: Enchantment) -> bool {
+        if self.get(&enchantment.name).is_some() {
+            return false;
+        }
+        self.0.push(enchantment);
+        true
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Enchantment> {
+        let index = self
+            .0
+            .iter()
+            .position(|enchantment| enchantment.name == name)?;
+        Some(self.0.remove(index))
+    }
+
+    /// Sum of the stats of all enchantments.
+    pub fn stats(&self) -> ItemProperties {
+        let mut stats = ItemProperties::default();
+        for enchantment in &self.0 {
+            stats.combine(&enchantment.stats);
+        }
+        stats
+    }
+}
+
+/// Enchantment offered by the [`EnchantingTable`] and the items it costs,
+/// loaded from `*.enchantment.ron`:
+///
+/// ```ron
+/// (
+///     enchantment: (name: "Efficiency", stats: (mining_power: 1.0)),
+///     cost: [(Path("items/item1.item.ron"), ItemStack(4))],
+/// )
+/// ```
+#[derive(Debug, Asset, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub struct EnchantmentDefinition {
+    pub enchantment: Enchantment,
+    pub cost: RecipeItems,
+}
+
+impl_asset_loader! {
+    EnchantmentDefinition &["enchantment.ron"];
+    cost
+}
+
+/// An enchanting table placed in the world, it offers every loaded [`EnchantmentDefinition`].
+#[derive(Component, Debug, Clone, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub struct EnchantingTable;
+
+/// Request to change the enchantments of the item in the `slot` of the player's inventory
+/// at the `table`. The player has to stand near the table and the item must be a single
+/// complex item; applying takes the enchantment cost from the inventory.
+#[derive(Debug, Clone, Event, Serialize, Deserialize)]
+pub struct EnchantItem {
+    pub table: Entity,
+    pub slot: usize,
+    pub action: EnchantAction,
+}
+
+impl MapEntities for EnchantItem {
+    fn map_entities<M: bevy::prelude::EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.table = entity_mapper.map_entity(self.table);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnchantAction {
+    /// Adds the enchantment with the name, its cost is taken from the inventory.
+    Apply(String),
+    /// Removes the enchantment with the name, the cost is not refunded.
+    Remove(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnchantError {
+    NoPlayer,
+    /// The player is too far from the table.
+    TooFar,
+    EmptySlot,
+    /// Only single complex items can be enchanted.
+    NotEnchantable,
+    UnknownEnchantment,
+    AlreadyEnchanted,
+    NotEnchanted,
+    MissingResources,
+    /// An item of the cost is not loaded, so the cost can't be checked.
+    UnresolvedCost,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Enchantment, ItemEnchantments};
+    use crate::plugins::crafting::logic::ItemProperties;
+
+    #[test]
+    fn enchantments_are_unique_and_removable() {
+        let efficiency =
+            ron::from_str::<Enchantment>(r#"(name: "Efficiency", stats: (mining_power: 1.0))"#)
+                .unwrap();
+        let swiftness = Enchantment {
+            name: "Swiftness".to_string(),
+            level: 2,
+            stats: ItemProperties {
+                speed: 0.1,
+                ..Default::default()
+            },
+        };
+
+        let mut enchantments = ItemEnchantments::default();
+        assert!(enchantments.add(efficiency.clone()));
+        assert!(!enchantments.add(efficiency.clone()));
+        assert!(enchantments.add(swiftness));
+        assert_eq!(enchantments.stats().mining_power, 1.0);
+        assert_eq!(enchantments.stats().speed, 0.1);
+
+        assert_eq!(enchantments.remove("Efficiency"), Some(efficiency));
+        assert_eq!(enchantments.remove("Efficiency"), None);
+        assert_eq!(enchantments.stats().mining_power, 0.0);
+    }
+}
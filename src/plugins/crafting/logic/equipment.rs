@@ -0,0 +1,195 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::{Entity, MapEntities},
+        event::Event,
+    },
+    reflect::{std_traits::ReflectDefault, Reflect},
+};
+use serde::{Deserialize, Serialize};
+
+/// Which [`EquipmentSlot`] an item can be equipped into, declared by the [`Item`](super::Item).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum EquipmentKind {
+    Head,
+    Body,
+    MainHand,
+    OffHand,
+    Accessory,
+}
+
+/// A slot of the [`Equipment`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    Head,
+    Body,
+    MainHand,
+    OffHand,
+    /// Index of the accessory slot, less than [`Equipment::ACCESSORIES`].
+    Accessory(usize),
+}
+
+impl EquipmentSlot {
+    pub fn kind(self) -> EquipmentKind {
+        match self {
+            EquipmentSlot::Head => EquipmentKind::Head,
+            EquipmentSlot::Body => EquipmentKind::Body,
+            EquipmentSlot::MainHand => EquipmentKind::MainHand,
+            EquipmentSlot::OffHand => EquipmentKind::OffHand,
+            EquipmentSlot::Accessory(_) => EquipmentKind::Accessory,
+        }
+    }
+
+    /// Every slot of the [`Equipment`].
+    pub fn all() -> impl Iterator<Item = EquipmentSlot> {
+        [
+            EquipmentSlot::Head,
+            EquipmentSlot::Body,
+            EquipmentSlot::MainHand,
+            EquipmentSlot::OffHand,
+        ]
+        .into_iter()
+        .chain((0..Equipment::ACCESSORIES).map(EquipmentSlot::Accessory))
+    }
+}
+
+/// Item entities equipped by a player. Equipped items are not in the player's
+/// [`Inventory`](super::Inventory), their stats are applied to the player.
+#[derive(Component, Debug, Clone, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub struct Equipment {
+    pub head: Option<Entity>,
+    pub body: Option<Entity>,
+    pub main_hand: Option<Entity>,
+    pub off_hand: Option<Entity>,
+    pub accessories: [Option<Entity>; Equipment::ACCESSORIES],
+}
+
+impl MapEntities for Equipment {
+    fn map_entities<M: bevy::prelude::EntityMapper>(&mut self, entity_mapper: &mut M) {
+        for slot in EquipmentSlot::all() {
+            if let Some(entity) = self.slot_mut(slot) {
+                *entity = entity.map(|entity| entity_mapper.map_entity(entity));
+            }
+        }
+    }
+}
+
+impl Equipment {
+    pub const ACCESSORIES: usize = 2;
+
+    pub fn get(&self, slot: EquipmentSlot) -> Option<Entity> {
+        match slot {
+            EquipmentSlot::Head => self.head,
+            EquipmentSlot::Body => self.body,
+            EquipmentSlot::MainHand => self.main_hand,
+            EquipmentSlot::OffHand => self.off_hand,
+            EquipmentSlot::Accessory(i) => self.accessories.get(i).copied().flatten(),
+        }
+    }
+
+    /// Returns `None` for accessory slots that don't exist.
+    fn slot_mut(&mut self, slot: EquipmentSlot) -> Option<&mut Option<Entity>> {
+        match slot {
+            EquipmentSlot::Head => Some(&mut self.head),
+            EquipmentSlot::Body => Some(&mut self.body),
+            EquipmentSlot::MainHand => Some(&mut self.main_hand),
+            EquipmentSlot::OffHand => Some(&mut self.off_hand),
+            EquipmentSlot::Accessory(i) => self.accessories.get_mut(i),
+        }
+    }
+
+    /// Puts the `entity` into the `slot` and returns the previously equipped entity.
+    /// Returns `Err` with the `entity` back if the slot doesn't exist.
+    pub fn equip(&mut self, slot: EquipmentSlot, entity: Entity) -> Result<Option<Entity>, Entity> {
+        match self.slot_mut(slot) {
+            Some(equipped) => Ok(equipped.replace(entity)),
+            None => Err(entity),
+        }
+    }
+
+    pub fn unequip(&mut self, slot: EquipmentSlot) -> Option<Entity> {
+        self.slot_mut(slot).and_then(Option::take)
+    }
+
+    /// Clears the slot holding the `entity`. Returns `false` if it's not equipped.
+    pub fn remove(&mut self, entity: Entity) -> bool {
+        let Some(slot) = self
+            .items()
+            .find(|(_, e)| *e == entity)
+            .map(|(slot, _)| slot)
+        else {
+            return false;
+        };
+        self.unequip(slot);
+        true
+    }
+
+    /// Equipped entities with their slots.
+    pub fn items(&self) -> impl Iterator<Item = (EquipmentSlot, Entity)> + '_ {
+        EquipmentSlot::all().filter_map(|slot| self.get(slot).map(|entity| (slot, entity)))
+    }
+}
+
+/// Request to equip the item in the `slot` of the player's inventory into the equipment slot.
+/// The item must declare the kind of the slot, only one item of a stack is equipped
+/// and the previously equipped item takes its place in the inventory.
+#[derive(Debug, Clone, Event, Serialize, Deserialize)]
+pub struct EquipItem {
+    pub slot: usize,
+    pub to: EquipmentSlot,
+}
+
+/// Request to move the item from the equipment slot into the player's inventory.
+#[derive(Debug, Clone, Event, Serialize, Deserialize)]
+pub struct UnequipItem {
+    pub slot: EquipmentSlot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EquipError {
+    NoPlayer,
+    EmptySlot,
+    /// The item can't be equipped into this slot.
+    WrongSlot,
+    /// There is no free inventory slot for the item.
+    InventoryFull,
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::entity::Entity;
+
+    use super::{Equipment, EquipmentKind, EquipmentSlot};
+
+    #[test]
+    fn equip_replaces_and_validates_slots() {
+        let mut equipment = Equipment::default();
+        let sword = Entity::from_raw(0);
+        let axe = Entity::from_raw(1);
+        let ring = Entity::from_raw(2);
+
+        assert_eq!(equipment.equip(EquipmentSlot::MainHand, sword), Ok(None));
+        assert_eq!(
+            equipment.equip(EquipmentSlot::MainHand, axe),
+            Ok(Some(sword))
+        );
+        assert_eq!(equipment.equip(EquipmentSlot::Accessory(1), ring), Ok(None));
+        assert_eq!(
+            equipment.equip(EquipmentSlot::Accessory(Equipment::ACCESSORIES), ring),
+            Err(ring)
+        );
+        assert_eq!(
+            equipment.items().collect::<Vec<_>>(),
+            vec![
+                (EquipmentSlot::MainHand, axe),
+                (EquipmentSlot::Accessory(1), ring)
+            ]
+        );
+        assert_eq!(EquipmentSlot::Accessory(1).kind(), EquipmentKind::Accessory);
+
+        assert!(equipment.remove(ring));
+        assert_eq!(equipment.unequip(EquipmentSlot::MainHand), Some(axe));
+        assert_eq!(equipment.items().count(), 0);
+    }
+}
@@ -0,0 +1,346 @@
+use bevy::{
+    asset::Assets,
+    ecs::system::Res,
+    log::{info, warn},
+    utils::hashbrown::{HashMap, HashSet},
+};
+
+use super::{Ingredient, Item, ItemId, Recipe, RecipeIndex};
+
+/// Dependencies between items through the recipes of all workbenches.
+/// An item depends on the ingredients of the recipes producing it.
+///
+/// Tag ingredients are treated as raw materials, any item with the tag will do.
+#[derive(Debug)]
+pub struct RecipeGraph<'a> {
+    items: HashMap<ItemId, &'a Item>,
+    /// Recipes producing the item in the order they were added to the index.
+    producers: HashMap<ItemId, Vec<&'a Recipe>>,
+}
+
+/// Amount of the raw materials needed to craft an item.
+pub type BillOfMaterials = Vec<(Ingredient, u32)>;
+
+/// Items that depend on each other, the first item depends on the second one and so on,
+/// the last item depends on the first one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecipeCycle(pub Vec<ItemId>);
+
+impl<'a> RecipeGraph<'a> {
+    /// Builds the graph from the `recipes` and all known `items`,
+    /// including those that are not used by any recipe.
+    pub fn new(recipes: &'a RecipeIndex, items: impl IntoIterator<Item = &'a Item>) -> Self {
+        let mut graph = Self {
+            items: items
+                .into_iter()
+                .map(|item| (item.id.clone(), item))
+                .collect(),
+            producers: HashMap::new(),
+        };
+        for recipe in recipes.recipes() {
+            for bundle in &recipe.output {
+                let producers = graph.producers.entry(bundle.item.id.clone()).or_default();
+                if !producers.iter().any(|r| std::ptr::eq(*r, recipe)) {
+                    producers.push(recipe);
+                }
+                graph
+                    .items
+                    .entry(bundle.item.id.clone())
+                    .or_insert(&bundle.item);
+            }
+            for (ingredient, _) in &recipe.input {
+                if let Ingredient::Item(item) = ingredient {
+                    graph.items.entry(item.id.clone()).or_insert(item);
+                }
+            }
+        }
+        graph
+    }
+
+    /// Items that are not produced by any recipe.
+    pub fn raw_materials(&self) -> Vec<&'a Item> {
+        let mut raw = self
+            .items
+            .iter()
+            .filter(|(key, _)| !self.producers.contains_key(*key))
+            .map(|(_, item)| *item)
+            .collect::<Vec<_>>();
+        raw.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+        raw
+    }
+
+    /// Items that can't be obtained: every recipe producing them needs an item
+    /// that can't be obtained either, e.g. because the recipes form a cycle.
+    /// Raw materials are always obtainable.
+    pub fn unreachable(&self) -> Vec<&'a Item> {
+        let mut reachable = self
+            .items
+            .keys()
+            .filter(|key| !self.producers.contains_key(*key))
+            .cloned()
+            .collect::<HashSet<_>>();
+
+        let obtainable = |reachable: &HashSet<ItemId>, ingredient: &Ingredient| match ingredient {
+            Ingredient::Item(item) => reachable.contains(&item.id),
+            Ingredient::Tag(tag) => reachable
+                .iter()
+                .any(|key| self.items.get(key).is_some_and(|item| item.has_tag(tag))),
+        };
+        loop {
+            let found = self
+                .producers
+                .iter()
+                .filter(|(key, _)| !reachable.contains(*key))
+                .filter(|(_, recipes)| {
+                    recipes.iter().any(|recipe| {
+                        recipe
+                            .input
+                            .iter()
+                            .all(|(ingredient, _)| obtainable(&reachable, ingredient))
+                    })
+                })
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+            if found.is_empty() {
+                break;
+            }
+            reachable.extend(found);
+        }
+
+        let mut unreachable = self
+            .items
+            .iter()
+            .filter(|(key, _)| !reachable.contains(*key))
+            .map(|(_, item)| *item)
+            .collect::<Vec<_>>();
+        unreachable.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+        unreachable
+    }
+
+    /// Finds the cycles between items through any of the recipes producing them.
+    /// Each cycle is reported once, starting from the item found first.
+    pub fn cycles(&self) -> Vec<RecipeCycle> {
+        let mut keys = self.producers.keys().collect::<Vec<_>>();
+        keys.sort_unstable();
+
+        let mut cycles = Vec::new();
+        let mut visited = HashSet::new();
+        for key in keys {
+            let mut path = Vec::new();
+            self.find_cycles(key, &mut path, &mut visited, &mut cycles);
+        }
+        cycles
+    }
+
+    fn find_cycles(
+        &self,
+        key: &ItemId,
+        path: &mut Vec<ItemId>,
+        visited: &mut HashSet<ItemId>,
+        cycles: &mut Vec<RecipeCycle>,
+    ) {
+        if let Some(start) = path.iter().position(|k| k == key) {
+            cycles.push(RecipeCycle(path[start..].to_vec()));
+            return;
+        }
+        if !visited.insert(key.clone()) {
+            return;
+        }
+
+        path.push(key.clone());
+        for dependency in self.dependencies(key) {
+            self.find_cycles(&dependency, path, visited, cycles);
+        }
+        path.pop();
+    }
+
+    /// Items used by the recipes producing the item.
+    fn dependencies(&self, key: &ItemId) -> Vec<ItemId> {
+        let mut dependencies = self
+            .producers
+            .get(key)
+            .into_iter()
+            .flatten()
+            .flat_map(|recipe| &recipe.input)
+            .filter_map(|(ingredient, _)| match ingredient {
+                Ingredient::Item(item) => Some(item.id.clone()),
+                Ingredient::Tag(_) => None,
+            })
+            .collect::<Vec<_>>();
+        dependencies.sort_unstable();
+        dependencies.dedup();
+        dependencies
+    }
+
+    /// Expands the recipes needed to craft `amount` of the `item` down to the raw materials.
+    /// The first recipe producing an item is used. The demand for an item is added up
+    /// over all recipes using it before its crafts are counted, so only the surplus
+    /// of its last craft is wasted.
+    pub fn bill_of_materials(
+        &self,
+        item: &Item,
+        amount: u32,
+    ) -> Result<BillOfMaterials, RecipeCycle> {
+        let item = Ingredient::Item(item.clone());
+        let mut order = Vec::new();
+        self.sort_ingredients(&item, &mut Vec::new(), &mut order)?;
+
+        // Every ingredient comes after the ingredients of the recipes using it
+        let mut demand = vec![(item, amount)];
+        let mut bill = Vec::new();
+        for ingredient in order.into_iter().rev() {
+            let needed = demand
+                .iter()
+                .find(|(i, _)| *i == ingredient)
+                .map_or(0, |(_, amount)| *amount);
+            let Some((recipe, produced)) = self.producer(&ingredient) else {
+                add_amount(&mut bill, ingredient, needed);
+                continue;
+            };
+            let crafts = needed.div_ceil(produced);
+            for (input, stack) in &recipe.input {
+                add_amount(&mut demand, input.clone(), stack.0 as u32 * crafts);
+            }
+        }
+        Ok(bill)
+    }
+
+    /// Pushes the `ingredient` into the `order` after the ingredients it's crafted from.
+    fn sort_ingredients(
+        &self,
+        ingredient: &Ingredient,
+        path: &mut Vec<ItemId>,
+        order: &mut Vec<Ingredient>,
+    ) -> Result<(), RecipeCycle> {
+        if order.contains(ingredient) {
+            return Ok(());
+        }
+        if let (Some((recipe, _)), Ingredient::Item(item)) = (self.producer(ingredient), ingredient)
+        {
+            if let Some(start) = path.iter().position(|k| *k == item.id) {
+                return Err(RecipeCycle(path[start..].to_vec()));
+            }
+            path.push(item.id.clone());
+            for (input, _) in &recipe.input {
+                self.sort_ingredients(input, path, order)?;
+            }
+            path.pop();
+        }
+        order.push(ingredient.clone());
+        Ok(())
+    }
+
+    /// The first recipe producing the `ingredient` and the amount produced by one craft.
+    /// Tags are never produced.
+    fn producer(&self, ingredient: &Ingredient) -> Option<(&'a Recipe, u32)> {
+        let Ingredient::Item(item) = ingredient else {
+            return None;
+        };
+        let recipe = *self.producers.get(&item.id)?.first()?;
+        let produced = recipe
+            .output
+            .iter()
+            .filter(|bundle| bundle.item.is_same_kind(item))
+            .map(|bundle| bundle.stack.0 as u32)
+            .sum::<u32>()
+            .max(1);
+        Some((recipe, produced))
+    }
+}
+
+fn add_amount(amounts: &mut Vec<(Ingredient, u32)>, ingredient: Ingredient, amount: u32) {
+    match amounts.iter_mut().find(|(i, _)| *i == ingredient) {
+        Some((_, total)) => *total += amount,
+        None => amounts.push((ingredient, amount)),
+    }
+}
+
+/// Reports recipe cycles and items that can't be obtained.
+pub(super) fn check_recipe_graph(index: Res<RecipeIndex>, item_assets: Res<Assets<Item>>) {
+    let graph = RecipeGraph::new(&index, item_assets.iter().map(|(_, item)| item));
+    for RecipeCycle(items) in graph.cycles() {
+        warn!("Recipes form a cycle: {items:?}");
+    }
+    let unreachable = graph.unreachable();
+    if !unreachable.is_empty() {
+        warn!(
+            "Items that can't be obtained: {:?}",
+            unreachable
+                .iter()
+                .map(|item| &item.name)
+                .collect::<Vec<_>>()
+        );
+    }
+    info!(
+        "Raw materials: {:?}",
+        graph
+            .raw_materials()
+            .iter()
+            .map(|item| &item.name)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::AssetId;
+
+    use super::{RecipeCycle, RecipeGraph};
+    use crate::plugins::crafting::logic::{
+        test_item, Ingredient, ItemBundle, ItemStack, RecipeIndex,
+    };
+
+    fn recipe(index: &mut RecipeIndex, input: &[(&str, u8)], output: (&str, u8)) {
+        index.insert(
+            AssetId::default(),
+            "Workbench",
+            input
+                .iter()
+                .map(|(name, amount)| (Ingredient::Item(test_item(name)), ItemStack(*amount)))
+                .collect(),
+            vec![ItemBundle {
+                item: test_item(output.0),
+                stack: ItemStack(output.1),
+            }],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn bill_of_materials_expands_chained_recipes() {
+        let mut index = RecipeIndex::default();
+        recipe(&mut index, &[("Log", 1)], ("Plank", 4));
+        recipe(&mut index, &[("Plank", 2)], ("Stick", 4));
+        recipe(&mut index, &[("Plank", 3), ("Stick", 2)], ("Axe", 1));
+        let graph = RecipeGraph::new(&index, []);
+
+        // 2 axes take 6 planks and 4 sticks, the sticks take 2 more planks,
+        // 8 planks are crafted from 2 logs.
+        assert_eq!(
+            graph.bill_of_materials(&test_item("Axe"), 2),
+            Ok(vec![(Ingredient::Item(test_item("Log")), 2)])
+        );
+        assert!(graph.cycles().is_empty());
+        assert!(graph.unreachable().is_empty());
+    }
+
+    #[test]
+    fn cycles_make_items_unreachable() {
+        let mut index = RecipeIndex::default();
+        recipe(&mut index, &[("Ingot", 1)], ("Ore", 1));
+        recipe(&mut index, &[("Ore", 1)], ("Ingot", 1));
+        let orphan = test_item("Gem");
+        let graph = RecipeGraph::new(&index, [&orphan]);
+
+        assert_eq!(graph.cycles().len(), 1);
+        assert!(matches!(
+            graph.bill_of_materials(&test_item("Ingot"), 1),
+            Err(RecipeCycle(items)) if items.len() == 2
+        ));
+        assert_eq!(
+            graph.unreachable(),
+            vec![&test_item("Ingot"), &test_item("Ore")]
+        );
+        assert_eq!(graph.raw_materials(), vec![&orphan]);
+    }
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Item, ItemBundle};
+
+/// Items placed by the player for a shaped recipe, row by row.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct CraftingGrid {
+    width: usize,
+    cells: Vec<Option<ItemBundle>>,
+}
+
+impl CraftingGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            cells: vec![None; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len().checked_div(self.width).unwrap_or(0)
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&ItemBundle> {
+        self.index(x, y).and_then(|i| self.cells[i].as_ref())
+    }
+
+    /// Places the `item` into the cell. Returns the previous one.
+    /// Does nothing if the cell is outside of the grid.
+    pub fn set(&mut self, x: usize, y: usize, item: Option<ItemBundle>) -> Option<ItemBundle> {
+        let i = self.index(x, y)?;
+        std::mem::replace(&mut self.cells[i], item)
+    }
+
+    /// Placed items, each cell separately.
+    pub fn items(&self) -> impl Iterator<Item = &ItemBundle> {
+        self.cells.iter().flatten()
+    }
+
+    pub fn shape(&self) -> Shape<Item> {
+        Shape::new(
+            self.width,
+            self.cells
+                .iter()
+                .map(|cell| cell.as_ref().map(|bundle| bundle.item.clone()))
+                .collect(),
+        )
+    }
+
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height()).then_some(y * self.width + x)
+    }
+}
+
+/// Pattern of items or ingredients without the empty rows and columns around it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shape<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<Option<T>>,
+}
+
+impl<T: Clone> Shape<T> {
+    /// Creates a shape from `cells` stored row by row and trims it.
+    pub fn new(width: usize, cells: Vec<Option<T>>) -> Self {
+        let height = cells.len().checked_div(width).unwrap_or(0);
+        let filled = |x: usize, y: usize| cells[y * width + x].is_some();
+
+        let columns = (0..width).filter(|&x| (0..height).any(|y| filled(x, y)));
+        let rows = (0..height).filter(|&y| (0..width).any(|x| filled(x, y)));
+        let (Some(min_x), Some(max_x)) = (columns.clone().min(), columns.max()) else {
+            return Self {
+                width: 0,
+                height: 0,
+                cells: Vec::new(),
+            };
+        };
+        let (min_y, max_y) = (rows.clone().min().unwrap(), rows.max().unwrap());
+
+        let mut trimmed = Vec::new();
+        for y in min_y..=max_y {
+            trimmed.extend_from_slice(&cells[y * width + min_x..=y * width + max_x]);
+        }
+
+        Self {
+            width: max_x - min_x + 1,
+            height: max_y - min_y + 1,
+            cells: trimmed,
+        }
+    }
+}
+
+impl<T> Shape<T> {
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Cells row by row.
+    pub fn cells(&self) -> &[Option<T>] {
+        &self.cells
+    }
+
+    /// Returns `true` if every cell of `other` or its mirror image is accepted
+    /// by the cell of this shape.
+    pub fn matches<U>(&self, other: &Shape<U>, accepts: impl Fn(&T, &U) -> bool) -> bool {
+        let cell_matches = |a: &Option<T>, b: &Option<U>| match (a, b) {
+            (None, None) => true,
+            (Some(a), Some(b)) => accepts(a, b),
+            _ => false,
+        };
+        if self.size() != other.size() {
+            return false;
+        }
+        let row = |y: usize| y * self.width..(y + 1) * self.width;
+
+        (0..self.height).all(|y| {
+            self.cells[row(y)]
+                .iter()
+                .zip(&other.cells[row(y)])
+                .all(|(a, b)| cell_matches(a, b))
+        }) || (0..self.height).all(|y| {
+            self.cells[row(y)]
+                .iter()
+                .zip(other.cells[row(y)].iter().rev())
+                .all(|(a, b)| cell_matches(a, b))
+        })
+    }
+}
@@ -0,0 +1,191 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        query::{Changed, QueryData},
+        system::{EntityCommands, Query},
+    },
+    log::info,
+    reflect::{std_traits::ReflectDefault, Reflect},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{Item, ItemEnchantments, ItemId, ItemStack};
+
+/// Wear of an item, it breaks when `current` reaches `0`.
+/// Items with durability don't stack, see [`ItemInstance::stack_limit`].
+#[derive(
+    Component, Default, Clone, PartialEq, Eq, Hash, Debug, Reflect, Serialize, Deserialize,
+)]
+#[reflect(Default)]
+pub struct Durability {
+    pub current: u16,
+    pub max: u16,
+}
+
+impl Durability {
+    pub fn new(max: u16) -> Self {
+        Self { current: max, max }
+    }
+
+    /// Reduces the durability by `amount`. Returns `true` if the item broke.
+    pub fn wear(&mut self, amount: u16) -> bool {
+        self.current = self.current.saturating_sub(amount);
+        self.is_broken()
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.current == 0
+    }
+}
+
+/// Name of the item given by a player, it's shown instead of [`Item::name`](super::Item::name).
+#[derive(
+    Component, Default, Clone, PartialEq, Eq, Hash, Debug, Reflect, Serialize, Deserialize,
+)]
+#[reflect(Default)]
+pub struct CustomName(pub String);
+
+/// Arbitrary key/value data of the item, e.g. the author of a book.
+/// Entries are sorted by the key, so the same data is always equal.
+#[derive(
+    Component, Default, Clone, PartialEq, Eq, Hash, Debug, Reflect, Serialize, Deserialize,
+)]
+#[reflect(Default)]
+pub struct ItemMetadata(Vec<(String, String)>);
+
+impl ItemMetadata {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .binary_search_by(|(k, _)| k.as_str().cmp(key))
+            .ok()
+            .map(|i| self.0[i].1.as_str())
+    }
+
+    /// Sets the `value` of the `key`, returns the previous one.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        let key = key.into();
+        let value = value.into();
+        match self.0.binary_search_by(|(k, _)| k.cmp(&key)) {
+            Ok(i) => Some(std::mem::replace(&mut self.0[i].1, value)),
+            Err(i) => {
+                self.0.insert(i, (key, value));
+                None
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// Per-instance data of an item entity. Stacks are merged only if their data is the same.
+#[derive(QueryData)]
+pub struct InstanceData {
+    pub durability: Option<&'static Durability>,
+    pub custom_name: Option<&'static CustomName>,
+    pub metadata: Option<&'static ItemMetadata>,
+    pub enchantments: Option<&'static ItemEnchantments>,
+}
+
+impl InstanceDataItem<'_> {
+    pub fn to_instance(&self) -> ItemInstance {
+        ItemInstance {
+            durability: self.durability.cloned(),
+            custom_name: self.custom_name.cloned(),
+            metadata: self.metadata.cloned(),
+            enchantments: self.enchantments.cloned(),
+        }
+    }
+}
+
+/// Owned copy of the [`InstanceData`] that is carried over to new item entities.
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ItemInstance {
+    pub durability: Option<Durability>,
+    pub custom_name: Option<CustomName>,
+    pub metadata: Option<ItemMetadata>,
+    pub enchantments: Option<ItemEnchantments>,
+}
+
+impl ItemInstance {
+    /// Items without instance data, they stack only with each other.
+    pub fn is_plain(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Stack limit of the `item` with this data. Items with durability are kept one per stack,
+    /// the worn ones would get the durability of the stack otherwise.
+    pub fn stack_limit(&self, item: &Item) -> u8 {
+        if self.durability.is_some() {
+            1
+        } else {
+            item.stack_limit()
+        }
+    }
+
+    pub fn matches(&self, data: &InstanceDataItem) -> bool {
+        self.durability.as_ref() == data.durability
+            && self.custom_name.as_ref() == data.custom_name
+            && self.metadata.as_ref() == data.metadata
+            && self.enchantments.as_ref() == data.enchantments
+    }
+
+    /// Inserts the data into the item entity.
+    pub fn insert(&self, entity: &mut EntityCommands) {
+        if let Some(durability) = &self.durability {
+            entity.insert(durability.clone());
+        }
+        if let Some(custom_name) = &self.custom_name {
+            entity.insert(custom_name.clone());
+        }
+        if let Some(metadata) = &self.metadata {
+            entity.insert(metadata.clone());
+        }
+        if let Some(enchantments) = &self.enchantments {
+            entity.insert(enchantments.clone());
+        }
+    }
+}
+
+/// Breaks the worn out items, their stacks are emptied.
+pub(crate) fn break_worn_items(
+    mut items: Query<(&ItemId, &mut ItemStack, &Durability), Changed<Durability>>,
+) {
+    for (id, mut stack, durability) in items.iter_mut() {
+        if !durability.is_broken() || stack.0 == 0 {
+            continue;
+        }
+        info!("Item {:?} broke", id.0);
+        stack.0 = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Durability, ItemMetadata};
+
+    #[test]
+    fn durability_wears_down() {
+        let mut durability = Durability::new(3);
+        assert!(!durability.wear(2));
+        assert!(durability.wear(5));
+        assert_eq!(durability, Durability { current: 0, max: 3 });
+    }
+
+    #[test]
+    fn metadata_order_does_not_matter() {
+        let mut a = ItemMetadata::default();
+        a.insert("author", "Alice");
+        a.insert("title", "Notes");
+        let mut b = ItemMetadata::default();
+        b.insert("title", "Draft");
+        b.insert("author", "Alice");
+        assert_ne!(a, b);
+
+        assert_eq!(b.insert("title", "Notes"), Some("Draft".to_string()));
+        assert_eq!(a, b);
+        assert_eq!(a.get("author"), Some("Alice"));
+        assert_eq!(a.get("year"), None);
+    }
+}
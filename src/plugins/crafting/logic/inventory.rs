@@ -1,23 +1,37 @@
+use std::cmp::Ordering;
+
 use bevy::{
     ecs::{
         component::Component,
         entity::{Entity, MapEntities},
+        event::Event,
         system::{Commands, Query},
     },
-    log::{error, info},
+    log::warn,
     reflect::{std_traits::ReflectDefault, Reflect},
 };
 use bevy_replicon::core::replication_rules::Replication;
 use serde::{Deserialize, Serialize};
 
-use super::{Item, ItemBundle, ItemStack, Layout};
+use super::{
+    CraftError, InstanceData, Item, ItemBundle, ItemDefinitions, ItemId, ItemInstance, ItemStack,
+    Layout,
+};
 
-#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+/// Fixed amount of slots, each slot may contain an item entity.
+/// Slot indices are stable, items never move unless they are moved explicitly.
+#[derive(Component, Reflect, Serialize, Deserialize)]
 #[reflect(Default)]
 pub struct Inventory {
     pub map: Vec<Option<Entity>>,
 }
 
+impl Default for Inventory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MapEntities for Inventory {
     fn map_entities<M: bevy::prelude::EntityMapper>(&mut self, entity_mapper: &mut M) {
         for (opt, ent) in self
@@ -30,15 +44,276 @@ impl MapEntities for Inventory {
     }
 }
 
+/// Request to move `amount` items from the `slot` of the `from` inventory
+/// into the `to` inventory. Items can only be taken from the player's own inventory
+/// or a nearby container; whatever doesn't fit stays in the source stack.
+#[derive(Debug, Clone, Event, Deserialize, Serialize)]
+pub struct TransferItem {
+    pub from: Entity,
+    pub to: Entity,
+    pub slot: usize,
+    pub amount: u8,
+}
+
+impl MapEntities for TransferItem {
+    fn map_entities<M: bevy::prelude::EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.from = entity_mapper.map_entity(self.from);
+        self.to = entity_mapper.map_entity(self.to);
+    }
+}
+
+/// Order of the stacks after [`Inventory::sort`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SortMode {
+    #[default]
+    Name,
+    /// Complex items first.
+    Kind,
+    /// Highest level first.
+    Level,
+    /// Largest stacks first.
+    Count,
+}
+
+impl SortMode {
+    pub const ALL: [SortMode; 4] = [
+        SortMode::Name,
+        SortMode::Kind,
+        SortMode::Level,
+        SortMode::Count,
+    ];
+
+    /// Compares two stacks in this order, ties are ordered by the name and the id.
+    pub fn compare(self, a: (&Item, &ItemStack), b: (&Item, &ItemStack)) -> Ordering {
+        let (a_item, a_stack) = a;
+        let (b_item, b_stack) = b;
+        let order = match self {
+            SortMode::Name => Ordering::Equal,
+            SortMode::Kind => b_item
+                .kind
+                .properties()
+                .is_some()
+                .cmp(&a_item.kind.properties().is_some()),
+            SortMode::Level => b_item.level.cmp(&a_item.level),
+            SortMode::Count => b_stack.0.cmp(&a_stack.0),
+        };
+        order
+            .then_with(|| a_item.name.cmp(&b_item.name))
+            .then_with(|| a_item.id.cmp(&b_item.id))
+    }
+}
+
+/// Request to sort the player's inventory, it's applied by the server.
+#[derive(Debug, Clone, Event, Deserialize, Serialize)]
+pub struct SortInventory {
+    pub mode: SortMode,
+}
+
 pub type ItemsLayout = Layout<ItemBundle>;
 
 impl Inventory {
+    pub const DEFAULT_CAPACITY: usize = 36;
+
     pub fn new() -> Self {
-        Self { map: Vec::new() }
+        Self::with_capacity(Self::DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            map: vec![None; capacity],
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns the entity stored in the `slot`.
+    pub fn get(&self, slot: usize) -> Option<Entity> {
+        self.map.get(slot).copied().flatten()
+    }
+
+    pub fn first_free_slot(&self) -> Option<usize> {
+        self.map.iter().position(Option::is_none)
     }
 
+    /// Moves entities from `other` into free slots of this inventory.
+    /// Entities that don't fit are left in `other`.
     pub fn join(&mut self, other: &mut Self) {
-        self.map.append(&mut other.map)
+        for slot in other.map.iter_mut().filter(|slot| slot.is_some()) {
+            let Some(id) = self.first_free_slot() else {
+                return;
+            };
+            self.map[id] = slot.take();
+        }
+    }
+
+    /// Puts the `entity` into the `slot`. Returns `false` if the slot is occupied or doesn't exist.
+    pub fn insert_at(&mut self, slot: usize, entity: Entity) -> bool {
+        match self.map.get_mut(slot) {
+            Some(opt @ None) => {
+                *opt = Some(entity);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `false` if any of the slots doesn't exist.
+    pub fn swap_slots(&mut self, a: usize, b: usize) -> bool {
+        if a >= self.capacity() || b >= self.capacity() {
+            return false;
+        }
+        self.map.swap(a, b);
+        true
+    }
+
+    /// Moves `amount` items from the stack in the `slot` into a new stack in the first free slot.
+    /// The new stack gets a copy of the instance data.
+    ///
+    /// Returns the slot of the new stack.
+    pub fn split_stack(
+        &mut self,
+        commands: &mut Commands,
+        query: &mut Query<(&ItemId, &mut ItemStack, InstanceData)>,
+        slot: usize,
+        amount: u8,
+    ) -> Option<usize> {
+        let entity = self.get(slot)?;
+        let free_slot = self.first_free_slot()?;
+        let (id, mut stack, data) = query.get_mut(entity).ok()?;
+        if amount == 0 || amount >= stack.0 {
+            return None;
+        }
+
+        stack.0 -= amount;
+        let new_entity = spawn_stack(commands, id, amount, &data.to_instance());
+        self.map[free_slot] = Some(new_entity);
+
+        Some(free_slot)
+    }
+
+    /// Takes `amount` items from the stack in the `slot` out of the inventory.
+    /// The whole stack entity is taken if the amount covers it, otherwise
+    /// a new stack with a copy of the instance data is split off.
+    ///
+    /// Returns the entity of the taken stack.
+    pub fn take_amount(
+        &mut self,
+        commands: &mut Commands,
+        query: &mut Query<(&ItemId, &mut ItemStack, InstanceData)>,
+        slot: usize,
+        amount: u8,
+    ) -> Option<Entity> {
+        let entity = self.get(slot)?;
+        let (id, mut stack, data) = query.get_mut(entity).ok()?;
+        if amount == 0 {
+            return None;
+        }
+        if amount >= stack.0 {
+            return self.take(slot);
+        }
+
+        stack.0 -= amount;
+        Some(spawn_stack(commands, id, amount, &data.to_instance()))
+    }
+
+    /// Moves as many items as possible from the stack in the `from` slot into
+    /// the stack of the same item with the same instance data in the `slot`.
+    /// The `from` slot is cleared when it gets empty.
+    ///
+    /// Returns the amount of moved items.
+    pub fn merge_into(
+        &mut self,
+        query: &mut Query<(&ItemId, &mut ItemStack, InstanceData)>,
+        definitions: &ItemDefinitions,
+        from: usize,
+        slot: usize,
+    ) -> u8 {
+        let (Some(from_entity), Some(to_entity)) = (self.get(from), self.get(slot)) else {
+            return 0;
+        };
+        let Ok([(from_id, mut from_stack, from_data), (to_id, mut to_stack, to_data)]) =
+            query.get_many_mut([from_entity, to_entity])
+        else {
+            return 0;
+        };
+        let same = from_id == to_id && from_data.to_instance().matches(&to_data);
+        let Some(item) = definitions.get(to_id).filter(|_| same) else {
+            return 0;
+        };
+
+        let limit = from_data.to_instance().stack_limit(item);
+        let remaining = to_stack.fill(from_stack.0, limit);
+        let moved = from_stack.0 - remaining;
+        from_stack.0 = remaining;
+        if remaining == 0 {
+            self.map[from] = None;
+        }
+
+        moved
+    }
+
+    /// Merges partial stacks of the same item with the same instance data and moves
+    /// the stacks into the first slots in the `mode` order. Items without a known
+    /// definition are kept after the others.
+    ///
+    /// Emptied stacks are left for `remove_empty_stacks` to despawn.
+    pub fn sort(
+        &mut self,
+        query: &mut Query<(&ItemId, &mut ItemStack, InstanceData)>,
+        definitions: &ItemDefinitions,
+        mode: SortMode,
+    ) {
+        let capacity = self.capacity();
+        for slot in 0..capacity {
+            for from in slot + 1..capacity {
+                self.merge_into(query, definitions, from, slot);
+            }
+        }
+
+        let mut known = vec![];
+        let mut unknown = vec![];
+        for entity in self.map.iter().flatten() {
+            match query
+                .get(*entity)
+                .ok()
+                .and_then(|(id, stack, _)| Some((definitions.get(id)?, stack.clone())))
+            {
+                Some((item, stack)) => known.push((*entity, item, stack)),
+                None => unknown.push(*entity),
+            }
+        }
+        known.sort_by(|(_, a_item, a_stack), (_, b_item, b_stack)| {
+            mode.compare((a_item, a_stack), (b_item, b_stack))
+        });
+
+        self.map = known
+            .into_iter()
+            .map(|(entity, ..)| entity)
+            .chain(unknown)
+            .map(Some)
+            .chain(std::iter::repeat(None))
+            .take(capacity)
+            .collect();
+    }
+
+    /// Items in the occupied slots. Items without a known definition are skipped.
+    pub fn items<'a>(
+        &self,
+        query: &'a Query<(&ItemId, &ItemStack)>,
+        definitions: &'a ItemDefinitions,
+    ) -> Vec<(&'a Item, &'a ItemStack)> {
+        self.map
+            .iter()
+            .flatten()
+            .filter_map(|entity| query.get(*entity).ok())
+            .filter_map(|(id, stack)| Some((definitions.get(id)?, stack)))
+            .collect()
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.map.contains(&Some(entity))
     }
 
     pub fn take_linear(&mut self, entity: Entity) -> Option<Entity> {
@@ -50,7 +325,7 @@ impl Inventory {
         None
     }
 
-    pub fn take_linear_item(&mut self, query: &Query<&Item>, item: &Item) -> Option<Entity> {
+    pub fn take_linear_item(&mut self, query: &Query<&ItemId>, item: &ItemId) -> Option<Entity> {
         for (id, item_ref) in self
             .map
             .iter()
@@ -70,7 +345,7 @@ impl Inventory {
 
     pub fn take_satisfying_layout(
         &mut self,
-        query: &Query<(&Item, &ItemStack)>,
+        query: &Query<(&ItemId, &ItemStack)>,
         layout: &ItemsLayout,
     ) -> Option<Vec<Entity>> {
         self.search_satisfying(query, layout).map(|ids| {
@@ -80,20 +355,20 @@ impl Inventory {
         })
     }
 
-    fn take(&mut self, id: usize) -> Option<Entity> {
+    pub fn take(&mut self, id: usize) -> Option<Entity> {
         self.map.get_mut(id).and_then(|opt| opt.take())
     }
 
     pub fn search_satisfying(
         &self,
-        query: &Query<(&Item, &ItemStack)>,
+        query: &Query<(&ItemId, &ItemStack)>,
         layout: &ItemsLayout,
     ) -> Option<Vec<usize>> {
         let items = layout.get();
         let mut vec = vec![];
         for ItemBundle { item, stack } in items {
             if let Some(id) = self.search_condition(query, |it, it_stack, _| {
-                it.name == item.name && it.kind == item.kind && it_stack.0 >= stack.0
+                *it == item.id && it_stack.0 >= stack.0
             }) {
                 vec.push(id)
             }
@@ -106,8 +381,8 @@ impl Inventory {
 
     pub fn search_condition(
         &self,
-        query: &Query<(&Item, &ItemStack)>,
-        condition: impl Fn(&Item, &ItemStack, Entity) -> bool,
+        query: &Query<(&ItemId, &ItemStack)>,
+        condition: impl Fn(&ItemId, &ItemStack, Entity) -> bool,
     ) -> Option<usize> {
         for (id, (it, stack), entity) in self
             .map
@@ -123,41 +398,179 @@ impl Inventory {
         None
     }
 
+    /// Adds items without instance data to the inventory. Existing stacks of the same item
+    /// are filled up to [`Item::stack_limit`] first, the remainder is spilled into new slots.
+    ///
+    /// Returns everything that could not fit into the inventory.
     pub fn add_combine(
         &mut self,
         commands: &mut Commands,
-        query: &mut Query<(&mut Item, &mut ItemStack)>,
+        query: &mut Query<(&ItemId, &mut ItemStack, InstanceData)>,
         items: Vec<(&Item, &ItemStack)>,
-    ) {
+    ) -> Vec<ItemBundle> {
+        let mut leftovers = vec![];
         for (item, stack) in items {
-            if let Some((id, item_entity)) = self
-                .search_condition(&query.to_readonly(), |it, _, _| {
-                    it.name == item.name && it.kind == item.kind
-                })
-                .and_then(|id| self.take(id).map(|ent| (id, ent)))
+            let remaining =
+                self.add_instance(commands, query, item, stack.0, &ItemInstance::default());
+            if remaining > 0 {
+                warn!(
+                    "Inventory is full, {remaining} of {:?} did not fit",
+                    item.name
+                );
+                leftovers.push(ItemBundle {
+                    item: item.clone(),
+                    stack: ItemStack(remaining),
+                });
+            }
+        }
+        leftovers
+    }
+
+    /// Adds `amount` items with the `instance` data like [`Inventory::add_combine`],
+    /// only stacks with the same data are filled, up to [`ItemInstance::stack_limit`].
+    ///
+    /// Returns the amount that could not fit into the inventory.
+    pub fn add_instance(
+        &mut self,
+        commands: &mut Commands,
+        query: &mut Query<(&ItemId, &mut ItemStack, InstanceData)>,
+        item: &Item,
+        amount: u8,
+        instance: &ItemInstance,
+    ) -> u8 {
+        let limit = instance.stack_limit(item);
+        let mut remaining = amount;
+
+        for entity in self.map.iter().flatten() {
+            if remaining == 0 {
+                break;
+            }
+            if let Ok((item_in_inventory, mut item_in_inventory_stack, data)) =
+                query.get_mut(*entity)
             {
-                info!("Found existing entity in inventory");
-                if let Ok((item_in_inventory, mut item_in_inventory_stack)) =
-                    query.get_mut(item_entity)
+                if *item_in_inventory == item.id
+                    && instance.matches(&data)
+                    && !item_in_inventory_stack.is_full(limit)
                 {
-                    info!("Passed");
-                    item_in_inventory_stack.0 += stack.0;
-                    dbg!(&item_in_inventory);
-                    self.map[id] = Some(item_entity);
-                } else {
-                    error!("Fail")
+                    remaining = item_in_inventory_stack.fill(remaining, limit);
+                }
+            }
+        }
+
+        while remaining > 0 {
+            let Some(id) = self.first_free_slot() else {
+                break;
+            };
+            let amount = remaining.min(limit);
+            remaining -= amount;
+            self.map[id] = Some(spawn_stack(commands, &item.id, amount, instance));
+        }
+        remaining
+    }
+
+    /// Checks that the `input` can be removed from the inventory and the `output`
+    /// can be added after that. The inventory is not changed.
+    ///
+    /// Removing is done like in [`Inventory::remove_item`], adding like in [`Inventory::add_combine`].
+    pub fn check_exchange(
+        &self,
+        query: &Query<(&ItemId, &ItemStack, InstanceData)>,
+        input: &[(&Item, &ItemStack)],
+        output: &[(&Item, &ItemStack)],
+    ) -> Result<(), CraftError> {
+        // `None` is a free slot, `Some(None)` is a slot occupied by an unknown entity,
+        // only stacks without instance data are filled with the output
+        let mut slots = self
+            .map
+            .iter()
+            .map(|opt| {
+                opt.map(|entity| {
+                    query
+                        .get(entity)
+                        .ok()
+                        .map(|(it, s, data)| (it, s.0, data.to_instance().is_plain()))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (item, stack) in input {
+            let mut remaining = stack.0;
+            for (slot_item, amount, _) in slots.iter_mut().flatten().flatten() {
+                if **slot_item == item.id {
+                    let removed = (*amount).min(remaining);
+                    *amount -= removed;
+                    remaining -= removed;
+                }
+            }
+            if remaining > 0 {
+                return Err(CraftError::MissingIngredients);
+            }
+        }
+
+        for slot in slots.iter_mut() {
+            if matches!(slot, Some(Some((_, 0, _)))) {
+                *slot = None;
+            }
+        }
+
+        for (item, stack) in output {
+            let limit = item.stack_limit();
+            let mut remaining = stack.0;
+            for (slot_item, amount, plain) in slots.iter_mut().flatten().flatten() {
+                if **slot_item == item.id && *plain {
+                    let mut slot_stack = ItemStack(*amount);
+                    remaining = slot_stack.fill(remaining, limit);
+                    *amount = slot_stack.0;
+                }
+            }
+            while remaining > 0 {
+                let Some(slot) = slots.iter_mut().find(|slot| slot.is_none()) else {
+                    return Err(CraftError::NotEnoughSpace);
+                };
+                let amount = remaining.min(limit);
+                remaining -= amount;
+                *slot = Some(Some((&item.id, amount, true)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes up to `amount` items with the `item` id, emptied slots are cleared.
+    /// Stacks without instance data are used up first, so named or enchanted items
+    /// are only taken when there is nothing else.
+    ///
+    /// Returns the amount of removed items.
+    pub fn remove_item(
+        &mut self,
+        query: &mut Query<(&ItemId, &mut ItemStack, InstanceData)>,
+        item: &ItemId,
+        amount: u8,
+    ) -> u8 {
+        let mut remaining = amount;
+        for plain in [true, false] {
+            for slot in self.map.iter_mut() {
+                if remaining == 0 {
+                    break;
+                }
+                let Some(Ok((item_in_inventory, mut stack, data))) =
+                    slot.map(|entity| query.get_mut(entity))
+                else {
+                    continue;
+                };
+                if item_in_inventory != item || data.to_instance().is_plain() != plain {
+                    continue;
+                }
+
+                let removed = stack.0.min(remaining);
+                stack.0 -= removed;
+                remaining -= removed;
+                if stack.0 == 0 {
+                    *slot = None;
                 }
-            } else {
-                info!("Adding single existing entity");
-                self.add_single_new(
-                    commands,
-                    ItemBundle {
-                        item: (*item).clone(),
-                        stack: (*stack).clone(),
-                    },
-                );
             }
         }
+        amount - remaining
     }
 
     pub fn add(&mut self, commands: &mut Commands, layout: ItemsLayout) {
@@ -168,11 +581,231 @@ impl Inventory {
     }
 
     fn add_single_new(&mut self, commands: &mut Commands, item: ItemBundle) {
-        let id = commands.spawn(item).insert(Replication).id();
-        self.map.push(Some(id))
+        let Some(slot) = self.first_free_slot() else {
+            warn!("Inventory is full, {:?} was not added", item.item.name);
+            return;
+        };
+        self.map[slot] = Some(spawn_stack(
+            commands,
+            &item.item.id,
+            item.stack.0,
+            &ItemInstance::default(),
+        ));
+    }
+
+    /// Puts the `entity` into the first free slot and returns this slot.
+    pub fn add_single(&mut self, entity: Entity) -> Option<usize> {
+        let slot = self.first_free_slot()?;
+        self.map[slot] = Some(entity);
+        Some(slot)
+    }
+}
+
+/// Spawns a replicated item entity, it references the definition by the `id`.
+fn spawn_stack(
+    commands: &mut Commands,
+    id: &ItemId,
+    amount: u8,
+    instance: &ItemInstance,
+) -> Entity {
+    let mut entity = commands.spawn((id.clone(), ItemStack(amount), Replication));
+    instance.insert(&mut entity);
+    entity.id()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::Assets,
+        ecs::{
+            entity::Entity,
+            system::{Commands, Query, RunSystemOnce},
+            world::World,
+        },
+    };
+
+    use super::{Inventory, SortMode};
+    use crate::plugins::crafting::logic::{
+        build_item_registry, CustomName, Durability, InstanceData, Item, ItemDefinitions, ItemId,
+        ItemKind, ItemProperties, ItemRegistry, ItemStack,
+    };
+
+    #[test]
+    fn sort_modes_order_stacks() {
+        let item = |name: &str, kind: ItemKind, level: u8| Item {
+            name: name.to_string(),
+            kind,
+            level,
+            ..Default::default()
+        };
+        let stick = item("Stick", ItemKind::Primitive, 1);
+        let axe = item("Axe", ItemKind::Complex(ItemProperties::default()), 2);
+        let ore = item("Ore", ItemKind::Primitive, 3);
+        let mut stacks = [
+            (&stick, ItemStack(5)),
+            (&axe, ItemStack(1)),
+            (&ore, ItemStack(9)),
+        ];
+        let mut sorted = |mode: SortMode| {
+            stacks.sort_by(|(a, a_stack), (b, b_stack)| mode.compare((a, a_stack), (b, b_stack)));
+            stacks
+                .iter()
+                .map(|(item, _)| item.name.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sorted(SortMode::Name), ["Axe", "Ore", "Stick"]);
+        assert_eq!(sorted(SortMode::Kind), ["Axe", "Ore", "Stick"]);
+        assert_eq!(sorted(SortMode::Level), ["Ore", "Axe", "Stick"]);
+        assert_eq!(sorted(SortMode::Count), ["Ore", "Stick", "Axe"]);
+    }
+
+    #[test]
+    fn sort_merges_stacks_of_spawned_items() {
+        let mut world = World::new();
+        let mut item_assets = Assets::<Item>::default();
+        for (id, max_stack) in [("ore", 10), ("stick", 16)] {
+            item_assets.add(Item {
+                id: ItemId(id.to_string()),
+                name: id.to_string(),
+                max_stack,
+                ..Default::default()
+            });
+        }
+        world.insert_resource(item_assets);
+        world.init_resource::<ItemRegistry>();
+        world.run_system_once(build_item_registry);
+
+        let mut spawn = |id: &str, amount| {
+            world
+                .spawn((ItemId(id.to_string()), ItemStack(amount)))
+                .id()
+        };
+        let ore = spawn("ore", 6);
+        let stick = spawn("stick", 4);
+        let more_ore = spawn("ore", 7);
+        let unknown = spawn("unknown", 1);
+        let worn_ore = spawn("ore", 1);
+        let more_sticks = spawn("stick", 5);
+        world.entity_mut(worn_ore).insert(Durability::new(10));
+
+        let mut inventory = Inventory::with_capacity(8);
+        for (slot, entity) in [ore, stick, more_ore, unknown, worn_ore, more_sticks]
+            .into_iter()
+            .enumerate()
+        {
+            assert!(inventory.insert_at(slot, entity));
+        }
+        let owner = world.spawn(inventory).id();
+        world.run_system_once(
+            |mut inventories: Query<&mut Inventory>,
+             mut items: Query<(&ItemId, &mut ItemStack, InstanceData)>,
+             definitions: ItemDefinitions| {
+                for mut inventory in inventories.iter_mut() {
+                    inventory.sort(&mut items, &definitions, SortMode::Name);
+                }
+            },
+        );
+
+        let inventory = world.get::<Inventory>(owner).unwrap();
+        let slots = [ore, more_ore, worn_ore, stick, unknown];
+        assert_eq!(inventory.map[..5], slots.map(Some));
+        assert!(inventory.map[5..].iter().all(Option::is_none));
+        let amounts = [ore, more_ore, worn_ore, stick, more_sticks]
+            .map(|entity| world.get::<ItemStack>(entity).unwrap().0);
+        assert_eq!(amounts, [10, 3, 1, 9, 0]);
     }
 
-    pub fn add_single(&mut self, entity: Entity) {
-        self.map.push(Some(entity));
+    #[test]
+    fn split_and_take_copy_instance_data() {
+        let mut world = World::new();
+        let name = CustomName("Lucky".to_string());
+        let ore = world
+            .spawn((ItemId("ore".to_string()), ItemStack(10), name.clone()))
+            .id();
+        let mut inventory = Inventory::with_capacity(3);
+        assert!(inventory.insert_at(0, ore));
+        let owner = world.spawn(inventory).id();
+
+        let (split, taken, too_many) = world.run_system_once(
+            move |mut commands: Commands,
+                  mut inventories: Query<&mut Inventory>,
+                  mut items: Query<(&ItemId, &mut ItemStack, InstanceData)>| {
+                let mut inventory = inventories.get_mut(owner).unwrap();
+                (
+                    inventory.split_stack(&mut commands, &mut items, 0, 4),
+                    inventory.take_amount(&mut commands, &mut items, 0, 2),
+                    inventory.split_stack(&mut commands, &mut items, 0, 4),
+                )
+            },
+        );
+
+        assert_eq!(split, Some(1));
+        assert_eq!(too_many, None);
+        let inventory = world.get::<Inventory>(owner).unwrap();
+        let split = inventory.get(1).unwrap();
+        assert_eq!(inventory.map, vec![Some(ore), Some(split), None]);
+        let taken = taken.unwrap();
+        assert!(!inventory.contains(taken));
+        for (entity, amount) in [(ore, 4), (split, 4), (taken, 2)] {
+            assert_eq!(world.get::<ItemStack>(entity), Some(&ItemStack(amount)));
+            assert_eq!(world.get::<CustomName>(entity), Some(&name));
+        }
+
+        let whole = world.run_system_once(
+            move |mut commands: Commands,
+                  mut inventories: Query<&mut Inventory>,
+                  mut items: Query<(&ItemId, &mut ItemStack, InstanceData)>| {
+                let mut inventory = inventories.get_mut(owner).unwrap();
+                inventory.take_amount(&mut commands, &mut items, 1, 4)
+            },
+        );
+        assert_eq!(whole, Some(split));
+        assert_eq!(world.get::<Inventory>(owner).unwrap().get(1), None);
+    }
+
+    #[test]
+    fn remove_item_takes_plain_stacks_first() {
+        let mut world = World::new();
+        let worn_ore = world
+            .spawn((ItemId("ore".to_string()), ItemStack(1), Durability::new(10)))
+            .id();
+        let ore = world.spawn((ItemId("ore".to_string()), ItemStack(2))).id();
+        let mut inventory = Inventory::with_capacity(2);
+        assert!(inventory.insert_at(0, worn_ore));
+        assert!(inventory.insert_at(1, ore));
+        let owner = world.spawn(inventory).id();
+
+        let removed = world.run_system_once(
+            move |mut inventories: Query<&mut Inventory>,
+                  mut items: Query<(&ItemId, &mut ItemStack, InstanceData)>| {
+                let mut inventory = inventories.get_mut(owner).unwrap();
+                inventory.remove_item(&mut items, &ItemId("ore".to_string()), 2)
+            },
+        );
+
+        assert_eq!(removed, 2);
+        let inventory = world.get::<Inventory>(owner).unwrap();
+        assert_eq!(inventory.map, vec![Some(worn_ore), None]);
+        assert_eq!(world.get::<ItemStack>(worn_ore), Some(&ItemStack(1)));
+    }
+
+    #[test]
+    fn slots_are_stable() {
+        let mut inventory = Inventory::with_capacity(3);
+        let a = Entity::from_raw(0);
+        let b = Entity::from_raw(1);
+
+        assert!(inventory.insert_at(2, a));
+        assert!(!inventory.insert_at(2, b));
+        assert!(!inventory.insert_at(3, b));
+        assert_eq!(inventory.add_single(b), Some(0));
+        assert_eq!(inventory.first_free_slot(), Some(1));
+
+        assert!(inventory.swap_slots(0, 1));
+        assert_eq!(inventory.get(1), Some(b));
+        assert_eq!(inventory.take_linear(a), Some(a));
+        assert_eq!(inventory.map, vec![None, Some(b), None]);
+        assert_eq!(inventory.capacity(), 3);
     }
 }
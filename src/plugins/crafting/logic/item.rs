@@ -1,23 +1,182 @@
 
 
+use std::hash::{Hash, Hasher};
+
 use bevy::{
-    asset::Asset,
+    asset::{Asset, AssetId, AssetLoader, Assets, AsyncReadExt},
     ecs::{
-        bundle::Bundle,
         component::Component,
         entity::{Entity, MapEntities},
         event::Event,
+        system::{Res, ResMut, Resource, SystemParam},
     },
+    log::warn,
     reflect::{std_traits::ReflectDefault, Reflect},
+    utils::hashbrown::HashMap,
 };
 use serde::{Deserialize, Serialize};
 
-#[derive(Component, Hash, Clone, PartialEq, Eq, Debug, Reflect, Serialize, Deserialize, Asset)]
+use super::EquipmentKind;
+
+/// Stable identifier of an [`Item`], the same on the server and on clients.
+/// Item entities store it instead of the whole definition.
+#[derive(
+    Component,
+    Default,
+    Clone,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Debug,
+    Reflect,
+    Serialize,
+    Deserialize,
+)]
+#[reflect(Default)]
+pub struct ItemId(pub String);
+
+impl ItemId {
+    /// Id of an item declared without one: its asset path without the extension,
+    /// e.g. `items/item1` for `items/item1.item.ron`.
+    pub fn from_path(path: &str) -> Self {
+        Self(path.strip_suffix(".item.ron").unwrap_or(path).to_string())
+    }
+}
+
+/// Definition of an item. Items are identified by their [`ItemId`],
+/// two definitions with the same id are the same item.
+#[derive(Clone, Debug, Reflect, Serialize, Deserialize, Asset)]
 #[reflect(Default)]
 pub struct Item {
+    /// Derived from the asset path if it's not declared.
+    #[serde(default)]
+    pub id: ItemId,
     pub name: String,
     pub kind: ItemKind,
     pub level: u8,
+    /// Maximum amount of items that a single [`ItemStack`] can hold.
+    #[serde(default = "Item::default_max_stack")]
+    pub max_stack: u8,
+    /// Groups of items, e.g. `"wood"`. Recipes can accept any item with a tag.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Equipment slot the item can be equipped into, `None` if it can't be equipped.
+    #[serde(default)]
+    pub equip: Option<EquipmentKind>,
+}
+
+impl PartialEq for Item {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Item {}
+
+impl Hash for Item {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl Item {
+    pub const DEFAULT_MAX_STACK: u8 = 64;
+
+    fn default_max_stack() -> u8 {
+        Self::DEFAULT_MAX_STACK
+    }
+
+    /// Stack limit of this item. Never returns `0` so a stack can always hold at least one item.
+    pub fn stack_limit(&self) -> u8 {
+        self.max_stack.max(1)
+    }
+
+    pub fn is_same_kind(&self, other: &Item) -> bool {
+        self.id == other.id
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Whether the name or a tag contains the `search` text, ignoring the case.
+    /// Every item matches an empty search.
+    pub fn matches_search(&self, search: &str) -> bool {
+        let search = search.trim().to_lowercase();
+        search.is_empty()
+            || self.name.to_lowercase().contains(&search)
+            || self
+                .tags
+                .iter()
+                .any(|tag| tag.to_lowercase().contains(&search))
+    }
+}
+
+/// Loaded item definitions by their [`ItemId`].
+#[derive(Resource, Debug, Default)]
+pub struct ItemRegistry(HashMap<ItemId, AssetId<Item>>);
+
+/// Resolves the [`ItemId`] of item entities to their definitions.
+#[derive(SystemParam)]
+pub struct ItemDefinitions<'w> {
+    registry: Res<'w, ItemRegistry>,
+    item_assets: Res<'w, Assets<Item>>,
+}
+
+impl ItemDefinitions<'_> {
+    pub fn get(&self, id: &ItemId) -> Option<&Item> {
+        self.registry
+            .0
+            .get(id)
+            .and_then(|asset| self.item_assets.get(*asset))
+    }
+}
+
+pub(crate) fn build_item_registry(
+    mut registry: ResMut<ItemRegistry>,
+    item_assets: Res<Assets<Item>>,
+) {
+    registry.0.clear();
+    for (asset, item) in item_assets.iter() {
+        if registry.0.insert(item.id.clone(), asset).is_some() {
+            warn!("Several items have the id {:?}", item.id.0);
+        }
+    }
+}
+
+/// Loads `*.item.ron` files and assigns the [`ItemId`] of items declared without one.
+#[derive(Default)]
+pub struct ItemAssetLoader;
+
+impl AssetLoader for ItemAssetLoader {
+    type Asset = Item;
+
+    type Settings = ();
+
+    type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let mut item = ron::de::from_bytes::<Item>(&bytes)?;
+            if item.id.0.is_empty() {
+                item.id = ItemId::from_path(&load_context.path().to_string_lossy());
+            }
+            Ok(item)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["item.ron"]
+    }
 }
 
 #[derive(Component, Hash, Clone, PartialEq, Eq, Debug, Reflect, Serialize, Deserialize)]
@@ -30,7 +189,23 @@ impl Default for ItemStack {
     }
 }
 
-#[derive(Bundle, PartialEq, Eq, Hash, Clone, Debug, Serialize, Deserialize)]
+impl ItemStack {
+    /// Adds up to `amount` items without exceeding `limit`.
+    /// Returns the amount that didn't fit into this stack.
+    pub fn fill(&mut self, amount: u8, limit: u8) -> u8 {
+        let space = limit.saturating_sub(self.0);
+        let added = space.min(amount);
+        self.0 += added;
+        amount - added
+    }
+
+    pub fn is_full(&self, limit: u8) -> bool {
+        self.0 >= limit
+    }
+}
+
+/// An item with its amount. Item entities consist of the [`ItemId`] and the [`ItemStack`].
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Serialize, Deserialize)]
 pub struct ItemBundle {
     pub item: Item,
     pub stack: ItemStack,
@@ -42,6 +217,8 @@ impl ItemBundle {
     }
 }
 
+/// Adds or removes items from the `inventory`.
+/// The player's own inventory is used if `inventory` is `None`.
 #[derive(Debug, Clone, Event, Deserialize, Serialize)]
 pub struct ItemEvent {
     pub kind: ItemEventKind,
@@ -49,7 +226,7 @@ pub struct ItemEvent {
     pub item: ItemBundle,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub enum ItemEventKind {
     Add,
     Remove,
@@ -61,19 +238,39 @@ impl MapEntities for ItemEvent {
     }
 }
 
+/// Sent by the server to the client whose [`ItemEvent`] was processed.
+#[derive(Debug, Clone, Event, Deserialize, Serialize)]
+pub struct ItemEventResponse {
+    pub kind: ItemEventKind,
+    /// `None` if the target inventory was not found.
+    pub inventory: Option<Entity>,
+    /// Items that were actually added or removed.
+    pub item: ItemBundle,
+    /// `true` if the whole requested amount was added or removed.
+    pub success: bool,
+}
+
+impl MapEntities for ItemEventResponse {
+    fn map_entities<M: bevy::prelude::EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.inventory = self.inventory.map(|ent| entity_mapper.map_entity(ent));
+    }
+}
+
 impl Default for Item {
     fn default() -> Self {
         Self {
+            id: ItemId::default(),
             name: "TestItem".to_string(),
             kind: ItemKind::Primitive,
             level: 1,
+            max_stack: Item::DEFAULT_MAX_STACK,
+            tags: Vec::new(),
+            equip: None,
         }
     }
 }
 
-#[derive(
-    Default, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Debug, Reflect, Serialize, Deserialize,
-)]
+#[derive(Default, Clone, PartialEq, Debug, Reflect, Serialize, Deserialize)]
 #[reflect(Default)]
 pub enum ItemKind {
     Complex(ItemProperties),
@@ -81,14 +278,90 @@ pub enum ItemKind {
     Primitive,
 }
 
-#[derive(
-    Hash, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Reflect, Default, Serialize, Deserialize,
-)]
+impl ItemKind {
+    /// Stats of complex items, primitive items have none.
+    pub fn properties(&self) -> Option<&ItemProperties> {
+        match self {
+            ItemKind::Complex(properties) => Some(properties),
+            ItemKind::Primitive => None,
+        }
+    }
+}
+
+/// Stat modifiers of a complex item, they are added up for the equipped items.
+/// Stats that are not declared in RON are `0`.
+#[derive(Clone, PartialEq, Debug, Reflect, Default, Serialize, Deserialize)]
 #[reflect(Default)]
-pub struct ItemProperties {}
+#[serde(default)]
+pub struct ItemProperties {
+    /// Movement speed bonus, `0.1` is 10% faster.
+    pub speed: f32,
+    /// Hardness of the blocks that can be mined.
+    pub mining_power: f32,
+}
 
-// impl Default for ItemProperties {
-//     fn default() -> Self {
-//         Self {}
-//     }
-// }
+impl ItemProperties {
+    /// Adds the stats of the `other` item.
+    pub fn combine(&mut self, other: &ItemProperties) {
+        self.speed += other.speed;
+        self.mining_power += other.mining_power;
+    }
+}
+
+/// Item whose id is its name, for the tests of the crafting logic.
+#[cfg(test)]
+pub(crate) fn test_item(name: &str) -> Item {
+    Item {
+        id: ItemId(name.to_string()),
+        name: name.to_string(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Item, ItemKind, ItemProperties, ItemStack};
+
+    #[test]
+    fn stack_fill_respects_limit() {
+        let mut stack = ItemStack(60);
+        assert_eq!(stack.fill(10, 64), 6);
+        assert_eq!(stack, ItemStack(64));
+        assert!(stack.is_full(64));
+
+        let mut stack = ItemStack(250);
+        assert_eq!(stack.fill(255, 255), 250);
+        assert_eq!(stack, ItemStack(255));
+    }
+
+    #[test]
+    fn search_matches_name_and_tags() {
+        let item = Item {
+            name: "Oak Log".to_string(),
+            tags: vec!["wood".to_string()],
+            ..Default::default()
+        };
+        assert!(item.matches_search(""));
+        assert!(item.matches_search(" oak "));
+        assert!(item.matches_search("WOO"));
+        assert!(!item.matches_search("stone"));
+    }
+
+    #[test]
+    fn complex_properties_from_ron() {
+        let kind = ron::from_str::<ItemKind>("Complex((speed: 0.25))").unwrap();
+        let mut total = ItemProperties {
+            mining_power: 2.0,
+            ..Default::default()
+        };
+        total.combine(kind.properties().unwrap());
+        assert_eq!(
+            total,
+            ItemProperties {
+                speed: 0.25,
+                mining_power: 2.0,
+            }
+        );
+        assert_eq!(ItemKind::Primitive.properties(), None);
+    }
+}
@@ -1,7 +1,7 @@
 use bevy::reflect::TypePath;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Deserialize, TypePath)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize, TypePath)]
 pub struct Layout<T>(pub Vec<T>);
 
 impl<T> Layout<T> {
@@ -0,0 +1,315 @@
+use bevy::{
+    app::Plugin,
+    asset::{Asset, AssetApp, Assets, AsyncReadExt, Handle},
+    ecs::system::Resource,
+    log::warn,
+    math::Vec3,
+    reflect::{std_traits::ReflectDefault, Reflect},
+};
+use bevy_asset_loader::asset_collection::AssetCollection;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    asset_macro::impl_asset_loader,
+    asset_ref::{AssetRef, Loadable},
+};
+
+use super::{Item, ItemStack};
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_asset::<LootTable>()
+            .register_asset_loader(LootTableAssetLoader)
+            .register_asset_reflect::<LootTable>();
+    }
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct LootTablesCollection {
+    #[asset(path = "loot_tables", collection(typed, mapped))]
+    tables: bevy::utils::HashMap<String, Handle<LootTable>>,
+}
+
+impl LootTablesCollection {
+    /// Loot table with the asset path, e.g. `loot_tables/chest.loot.ron`.
+    pub fn get(&self, path: &str) -> Option<&Handle<LootTable>> {
+        self.tables.get(path)
+    }
+}
+
+/// Weighted entries rolled to fill chests and enemy drops, loaded from `*.loot.ron`:
+///
+/// ```ron
+/// (
+///     rolls: (min: 1, max: 3),
+///     entries: [
+///         (weight: 4, loot: Item(item: Path("items/item1.item.ron"), count: (min: 1, max: 8))),
+///         (loot: Table(Path("loot_tables/rare.loot.ron")), conditions: [Chance(0.5)]),
+///         (weight: 2, loot: Nothing),
+///     ],
+/// )
+/// ```
+#[derive(Debug, Clone, Asset, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub struct LootTable {
+    /// How many entries are picked.
+    #[serde(default)]
+    pub rolls: Quantity,
+    pub entries: Vec<LootEntry>,
+}
+
+impl_asset_loader! {
+    LootTable &["loot.ron"];
+    entries
+}
+
+/// Inclusive range of an amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub struct Quantity {
+    pub min: u8,
+    pub max: u8,
+}
+
+impl Default for Quantity {
+    fn default() -> Self {
+        Self { min: 1, max: 1 }
+    }
+}
+
+impl Quantity {
+    fn roll(&self, rng: &mut ChaCha8Rng) -> u8 {
+        rng.gen_range(self.min..=self.max.max(self.min))
+    }
+}
+
+#[derive(Debug, Clone, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub struct LootEntry {
+    /// Chance of the entry relative to the other entries whose conditions hold.
+    #[serde(default = "LootEntry::default_weight")]
+    pub weight: u32,
+    pub loot: Loot,
+    /// All of them have to hold for the entry to be picked.
+    #[serde(default)]
+    pub conditions: Vec<LootCondition>,
+}
+
+impl LootEntry {
+    fn default_weight() -> u32 {
+        1
+    }
+}
+
+impl Loadable for LootEntry {
+    fn load(&mut self, load_context: &mut bevy::asset::LoadContext) {
+        match &mut self.loot {
+            Loot::Item { item, .. } => item.load(load_context),
+            Loot::Table(table) => table.load(load_context),
+            Loot::Nothing => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub enum Loot {
+    Item {
+        item: AssetRef<Item>,
+        #[serde(default)]
+        count: Quantity,
+    },
+    /// Rolls the nested table.
+    Table(AssetRef<LootTable>),
+    /// Makes the other entries less likely.
+    #[default]
+    Nothing,
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum LootCondition {
+    /// Holds with the probability from `0.0` to `1.0`.
+    Chance(f32),
+    Source(LootSource),
+    /// Holds if the level of the roll is at least this.
+    MinLevel(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum LootSource {
+    Chest,
+    Enemy,
+}
+
+/// What the loot is rolled for, checked by the [`LootCondition`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LootContext {
+    pub source: LootSource,
+    pub level: u8,
+}
+
+impl LootCondition {
+    fn holds(&self, context: &LootContext, rng: &mut ChaCha8Rng) -> bool {
+        match self {
+            LootCondition::Chance(chance) => rng.gen::<f32>() < *chance,
+            LootCondition::Source(source) => context.source == *source,
+            LootCondition::MinLevel(level) => context.level >= *level,
+        }
+    }
+}
+
+impl LootTable {
+    /// Nested tables deeper than this are not rolled, so cycles can't hang the game.
+    pub const MAX_DEPTH: usize = 8;
+
+    /// Seed of the loot placed at the `position` in the world with the `world_seed`,
+    /// so the same world always gets the same loot at the same place.
+    pub fn position_seed(world_seed: u32, position: Vec3) -> u64 {
+        position
+            .to_array()
+            .into_iter()
+            .fold(u64::from(world_seed), |seed, coordinate| {
+                (seed ^ u64::from(coordinate.to_bits())).wrapping_mul(0x100_0000_01b3)
+            })
+    }
+
+    /// Rolls the table, the same `seed` always gives the same items on every platform.
+    /// Nested tables are taken from the `tables`, [`validate_assets`](super::validate_assets)
+    /// makes sure they are loaded.
+    pub fn roll<'a>(
+        &'a self,
+        seed: u64,
+        context: &LootContext,
+        tables: &'a Assets<LootTable>,
+    ) -> Vec<(&'a AssetRef<Item>, ItemStack)> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut loot = vec![];
+        self.roll_into(&mut rng, context, tables, 0, &mut loot);
+        loot
+    }
+
+    /// Rolls the table like [`LootTable::roll`] and resolves the items.
+    pub fn roll_items<'a>(
+        &self,
+        seed: u64,
+        context: &LootContext,
+        tables: &Assets<LootTable>,
+        items: &'a Assets<Item>,
+    ) -> Vec<(&'a Item, ItemStack)> {
+        self.roll(seed, context, tables)
+            .into_iter()
+            .filter_map(|(item, stack)| {
+                let resolved = item.get_handle().and_then(|handle| items.get(handle));
+                if resolved.is_none() {
+                    warn!("Rolled an unloaded item {item:?}, it's skipped");
+                }
+                Some((resolved?, stack))
+            })
+            .collect()
+    }
+
+    fn roll_into<'a>(
+        &'a self,
+        rng: &mut ChaCha8Rng,
+        context: &LootContext,
+        tables: &'a Assets<LootTable>,
+        depth: usize,
+        loot: &mut Vec<(&'a AssetRef<Item>, ItemStack)>,
+    ) {
+        if depth > Self::MAX_DEPTH {
+            return;
+        }
+        for _ in 0..self.rolls.roll(rng) {
+            let candidates = self
+                .entries
+                .iter()
+                .filter(|entry| entry.weight > 0)
+                .filter(|entry| entry.conditions.iter().all(|c| c.holds(context, rng)))
+                .collect::<Vec<_>>();
+            let total = candidates.iter().map(|entry| entry.weight).sum::<u32>();
+            if total == 0 {
+                continue;
+            }
+
+            let mut pick = rng.gen_range(0..total);
+            let Some(entry) = candidates.into_iter().find(|entry| {
+                let found = pick < entry.weight;
+                pick = pick.saturating_sub(entry.weight);
+                found
+            }) else {
+                continue;
+            };
+            match &entry.loot {
+                Loot::Item { item, count } => {
+                    let amount = count.roll(rng);
+                    if amount > 0 {
+                        loot.push((item, ItemStack(amount)));
+                    }
+                }
+                Loot::Table(table) => {
+                    match table.get_handle().and_then(|handle| tables.get(handle)) {
+                        Some(table) => table.roll_into(rng, context, tables, depth + 1, loot),
+                        None => warn!("Rolled an unloaded loot table {table:?}, it's skipped"),
+                    }
+                }
+                Loot::Nothing => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::Assets;
+
+    use super::{LootContext, LootSource, LootTable};
+    use crate::asset_ref::AssetRef;
+
+    const CHEST: LootContext = LootContext {
+        source: LootSource::Chest,
+        level: 1,
+    };
+
+    #[test]
+    fn rolls_are_seeded_and_respect_conditions() {
+        let mut tables = Assets::<LootTable>::default();
+        let rare = ron::from_str::<LootTable>(
+            r#"(entries: [(loot: Item(item: Path("items/sword.item.ron")))])"#,
+        )
+        .unwrap();
+        let rare = tables.add(rare);
+
+        let mut table = ron::from_str::<LootTable>(
+            r#"(
+                rolls: (min: 4, max: 6),
+                entries: [
+                    (weight: 3, loot: Item(item: Path("items/item1.item.ron"), count: (min: 2, max: 5))),
+                    (loot: Table(Path("")), conditions: [MinLevel(2)]),
+                    (weight: 0, loot: Item(item: Path("items/item2.item.ron"))),
+                    (loot: Item(item: Path("items/item2.item.ron")), conditions: [Source(Enemy)]),
+                    (loot: Nothing),
+                ],
+            )"#,
+        )
+        .unwrap();
+        table.entries[1].loot = super::Loot::Table(AssetRef::Handle(rare));
+
+        let loot = table.roll(7, &CHEST, &tables);
+        assert_eq!(loot, table.roll(7, &CHEST, &tables));
+        for seed in 0..50 {
+            for (item, stack) in table.roll(seed, &CHEST, &tables) {
+                assert_eq!(item.get_path().as_deref(), Some("items/item1.item.ron"));
+                assert!((2..=5).contains(&stack.0));
+            }
+        }
+
+        let high_level = LootContext { level: 2, ..CHEST };
+        assert!((0..50)
+            .flat_map(|seed| table.roll(seed, &high_level, &tables))
+            .any(|(item, _)| item.get_path().as_deref() == Some("items/sword.item.ron")));
+    }
+}
@@ -1,9 +1,31 @@
+mod dropped;
+mod enchantment;
+mod equipment;
+mod graph;
+mod grid;
+mod instance;
 mod inventory;
 mod item;
 mod layout;
+mod loot;
+mod recipes;
+mod reload;
+mod station;
+mod validation;
 mod workbenches;
 
+pub use dropped::*;
+pub use enchantment::*;
+pub use equipment::*;
+pub use graph::*;
+pub use grid::*;
+pub use instance::*;
 pub use inventory::*;
 pub use item::*;
 pub use layout::*;
+pub use loot::*;
+pub use recipes::*;
+pub use reload::*;
+pub use station::*;
+pub use validation::*;
 pub use workbenches::*;
@@ -0,0 +1,958 @@
+use bevy::{
+    asset::{AssetId, Assets},
+    ecs::{
+        component::Component,
+        system::{Res, ResMut, Resource},
+    },
+    log::warn,
+    utils::hashbrown::{HashMap, HashSet},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    CraftInput, CraftingGrid, IngredientRef, Item, ItemBundle, ItemId, ItemStack, ItemsLayout,
+    Layout, RecipeItems, Shape, ShapedRecipe, Workbench,
+};
+
+/// Resolved [`IngredientRef`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Ingredient {
+    Item(Item),
+    /// Any item with the tag.
+    Tag(String),
+}
+
+impl Ingredient {
+    pub fn accepts(&self, item: &Item) -> bool {
+        match self {
+            Ingredient::Item(ingredient) => ingredient.is_same_kind(item),
+            Ingredient::Tag(tag) => item.has_tag(tag),
+        }
+    }
+}
+
+/// Identifies a [`Recipe`] by its workbench, ingredients and output, so it's the same
+/// on the server and on clients regardless of the asset loading order.
+/// It's the 64-bit FNV-1a hash of their names and amounts, which unlike
+/// [`std::hash::DefaultHasher`] doesn't change between Rust releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RecipeId(pub u64);
+
+impl RecipeId {
+    fn new(
+        workbench: &str,
+        input: &[(Ingredient, ItemStack)],
+        output: &[ItemBundle],
+        shape: Option<&Shape<Ingredient>>,
+    ) -> Self {
+        let mut hasher = Fnv1a::default();
+        hasher.write_str(workbench);
+        hasher.write_len(input.len());
+        for (ingredient, stack) in input {
+            hasher.write_ingredient(Some(ingredient));
+            hasher.write(&[stack.0]);
+        }
+        hasher.write_len(output.len());
+        for ItemBundle { item, stack } in output {
+            hasher.write_str(&item.id.0);
+            hasher.write(&[stack.0]);
+        }
+        if let Some(shape) = shape {
+            hasher.write_len(shape.size().0);
+            hasher.write_len(shape.cells().len());
+            for cell in shape.cells() {
+                hasher.write_ingredient(cell.as_ref());
+            }
+        }
+        Self(hasher.0)
+    }
+}
+
+/// 64-bit FNV-1a hasher. Lengths are written before strings and lists,
+/// so different recipes don't write the same bytes.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_len(&mut self, len: usize) {
+        self.write(&(len as u32).to_le_bytes());
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write_len(s.len());
+        self.write(s.as_bytes());
+    }
+
+    fn write_ingredient(&mut self, ingredient: Option<&Ingredient>) {
+        match ingredient {
+            None => self.write(&[0]),
+            Some(Ingredient::Item(item)) => {
+                self.write(&[1]);
+                self.write_str(&item.id.0);
+            }
+            Some(Ingredient::Tag(tag)) => {
+                self.write(&[2]);
+                self.write_str(tag);
+            }
+        }
+    }
+}
+
+/// A [`Workbench`] recipe with resolved items.
+#[derive(Debug, Clone)]
+pub struct Recipe {
+    pub id: RecipeId,
+    pub workbench: AssetId<Workbench>,
+    /// Required amount of every ingredient. Each ingredient is listed once.
+    pub input: Vec<(Ingredient, ItemStack)>,
+    pub output: Vec<ItemBundle>,
+    /// Pattern of a shaped recipe. `None` for shapeless recipes.
+    pub shape: Option<Shape<Ingredient>>,
+    /// Crafting time in seconds.
+    pub duration: f32,
+}
+
+impl Recipe {
+    fn has_tags(&self) -> bool {
+        self.input
+            .iter()
+            .any(|(ingredient, _)| matches!(ingredient, Ingredient::Tag(_)))
+    }
+
+    /// Assigns the `offered` items to the ingredients, exact items first, then tags.
+    /// What's left of an item after its own ingredient goes to the other ingredients
+    /// accepting it. Returns the items to consume, or `None` if an offered item
+    /// is not accepted or there is not enough of some ingredient.
+    fn consume(&self, offered: &[ItemBundle]) -> Option<Vec<ItemBundle>> {
+        let offered = merge(offered);
+        if offered
+            .iter()
+            .any(|(item, _)| !self.input.iter().any(|(i, _)| i.accepts(item)))
+        {
+            return None;
+        }
+
+        let assigned = self.assign(&offered)?;
+        offered
+            .iter()
+            .zip(assigned)
+            .filter_map(|((item, _), taken)| {
+                let taken = taken.iter().sum::<u32>();
+                (taken > 0).then(|| {
+                    Some(ItemBundle {
+                        item: (*item).clone(),
+                        stack: ItemStack(u8::try_from(taken).ok()?),
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// Distributes the `items` with their amounts between the ingredients.
+    /// Returns how much of every item is used for every ingredient,
+    /// or `None` if there is not enough of some ingredient.
+    fn assign(&self, items: &[(&Item, u32)]) -> Option<Vec<Vec<u32>>> {
+        let amounts = items.iter().map(|(_, amount)| *amount).collect::<Vec<_>>();
+        let required = self
+            .input
+            .iter()
+            .map(|(_, stack)| stack.0 as u32)
+            .collect::<Vec<_>>();
+        // Exact items are tried before tags, so they are used for their own ingredient.
+        let mut ingredients = (0..self.input.len()).collect::<Vec<_>>();
+        ingredients.sort_by_key(|&i| matches!(self.input[i].0, Ingredient::Tag(_)));
+        let accepts =
+            |item: usize, ingredient: usize| self.input[ingredient].0.accepts(items[item].0);
+        distribute(&amounts, &required, &ingredients, accepts)
+    }
+
+    /// Picks the items for this recipe from the `available` ones, e.g. from an inventory.
+    /// Returns `None` if there are not enough of them.
+    pub fn offer(&self, available: &[(&Item, &ItemStack)]) -> Option<CraftInput> {
+        let available = available
+            .iter()
+            .map(|(item, stack)| ItemBundle {
+                item: (*item).clone(),
+                stack: (*stack).clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let Some(shape) = &self.shape else {
+            let offered = available
+                .into_iter()
+                .filter(|bundle| {
+                    self.input
+                        .iter()
+                        .any(|(ingredient, _)| ingredient.accepts(&bundle.item))
+                })
+                .collect::<Vec<_>>();
+            self.consume(&offered)?;
+            return Some(CraftInput::Shapeless(Layout(offered)));
+        };
+
+        // Cells of the same ingredient may be filled with different items,
+        // so the items are distributed by their amounts first.
+        let have = merge(&available);
+        let mut assigned = self.assign(&have)?;
+        let (width, height) = shape.size();
+        let mut grid = CraftingGrid::new(width, height);
+        for (i, ingredient) in shape.cells().iter().enumerate() {
+            let Some(ingredient) = ingredient else {
+                continue;
+            };
+            let j = self
+                .input
+                .iter()
+                .position(|(other, _)| other == ingredient)?;
+            let k = assigned.iter().position(|amounts| amounts[j] > 0)?;
+            assigned[k][j] -= 1;
+            grid.set(
+                i % width,
+                i / width,
+                Some(ItemBundle {
+                    item: have[k].0.clone(),
+                    stack: ItemStack(1),
+                }),
+            );
+        }
+        Some(CraftInput::Shaped(grid))
+    }
+}
+
+/// A [`Recipe`] that can be crafted from the offered items.
+#[derive(Debug)]
+pub struct RecipeMatch<'a> {
+    pub recipe: &'a Recipe,
+    /// Offered items that the recipe takes.
+    pub consumed: Vec<ItemBundle>,
+}
+
+/// Recipes of all loaded workbenches. Built once the assets are loaded,
+/// so crafting doesn't have to resolve asset handles.
+#[derive(Resource, Debug, Default)]
+pub struct RecipeIndex {
+    recipes: Vec<Recipe>,
+    /// Indices of the shapeless recipes without tags grouped by their set of items.
+    by_ingredients: HashMap<Vec<ItemId>, Vec<usize>>,
+    /// Indices of the shapeless recipes with tags, they can't be grouped by items.
+    tagged: HashMap<AssetId<Workbench>, Vec<usize>>,
+    /// Indices of the shaped recipes grouped by their size.
+    by_shape: HashMap<(usize, usize), Vec<usize>>,
+    by_workbench: HashMap<AssetId<Workbench>, Vec<usize>>,
+    /// Indices of the recipes producing the item.
+    by_output: HashMap<ItemId, Vec<usize>>,
+    /// Indices of the recipes with the item as an ingredient.
+    by_input: HashMap<ItemId, Vec<usize>>,
+    /// Indices of the recipes with the tag as an ingredient.
+    by_tag: HashMap<String, Vec<usize>>,
+}
+
+impl RecipeIndex {
+    /// Resolves and adds all recipes of the `workbench`.
+    /// Recipes with items that are not loaded are skipped.
+    pub fn add_workbench(
+        &mut self,
+        id: AssetId<Workbench>,
+        workbench: &Workbench,
+        item_assets: &Assets<Item>,
+    ) {
+        let resolve = |items: &RecipeItems| {
+            items
+                .iter()
+                .map(|(item, stack)| {
+                    item.get_handle()
+                        .and_then(|h| item_assets.get(h))
+                        .map(|item| ItemBundle {
+                            item: item.clone(),
+                            stack: stack.clone(),
+                        })
+                })
+                .collect::<Option<Vec<_>>>()
+        };
+
+        for (input, output) in workbench.recipes() {
+            let input = input
+                .iter()
+                .map(|(ingredient, stack)| {
+                    resolve_ingredient(ingredient, item_assets).map(|i| (i, stack.clone()))
+                })
+                .collect::<Option<Vec<_>>>();
+            let (Some(input), Some(items)) = (input, resolve(&output.items)) else {
+                warn!(
+                    "Skipping a recipe of {:?} with items that are not loaded",
+                    workbench.name()
+                );
+                continue;
+            };
+            self.insert(id, workbench.name(), input, items, output.time);
+        }
+
+        for recipe in workbench.shaped() {
+            let (Some(shape), Some(output)) = (
+                resolve_pattern(recipe, item_assets),
+                resolve(&recipe.output),
+            ) else {
+                warn!(
+                    "Skipping a shaped recipe of {:?} with unknown keys or items that are not loaded",
+                    workbench.name()
+                );
+                continue;
+            };
+            self.insert_shaped(id, workbench.name(), shape, output, recipe.time);
+        }
+    }
+
+    pub fn insert(
+        &mut self,
+        workbench: AssetId<Workbench>,
+        workbench_name: &str,
+        input: Vec<(Ingredient, ItemStack)>,
+        output: Vec<ItemBundle>,
+        duration: f32,
+    ) {
+        let input = merge_ingredients(input);
+        let recipe = Recipe {
+            id: RecipeId::new(workbench_name, &input, &output, None),
+            workbench,
+            input,
+            output,
+            shape: None,
+            duration,
+        };
+
+        if recipe.has_tags() {
+            self.tagged.entry(workbench).or_default()
+        } else {
+            let mut keys = recipe
+                .input
+                .iter()
+                .filter_map(|(ingredient, _)| match ingredient {
+                    Ingredient::Item(item) => Some(item.id.clone()),
+                    Ingredient::Tag(_) => None,
+                })
+                .collect::<Vec<_>>();
+            keys.sort_unstable();
+            self.by_ingredients.entry(keys).or_default()
+        }
+        .push(self.recipes.len());
+        self.push(recipe);
+    }
+
+    /// Adds a shaped recipe. Every cell of the pattern takes one item.
+    pub fn insert_shaped(
+        &mut self,
+        workbench: AssetId<Workbench>,
+        workbench_name: &str,
+        shape: Shape<Ingredient>,
+        output: Vec<ItemBundle>,
+        duration: f32,
+    ) {
+        let input = merge_ingredients(
+            shape
+                .cells()
+                .iter()
+                .flatten()
+                .map(|ingredient| (ingredient.clone(), ItemStack(1)))
+                .collect(),
+        );
+
+        self.by_shape
+            .entry(shape.size())
+            .or_default()
+            .push(self.recipes.len());
+        self.push(Recipe {
+            id: RecipeId::new(workbench_name, &input, &output, Some(&shape)),
+            workbench,
+            input,
+            output,
+            shape: Some(shape),
+            duration,
+        });
+    }
+
+    /// Adds the `recipe` to the indices shared by shapeless and shaped recipes.
+    fn push(&mut self, recipe: Recipe) {
+        let i = self.recipes.len();
+        self.by_workbench
+            .entry(recipe.workbench)
+            .or_default()
+            .push(i);
+        for bundle in &recipe.output {
+            push_unique(self.by_output.entry(bundle.item.id.clone()).or_default(), i);
+        }
+        for (ingredient, _) in &recipe.input {
+            match ingredient {
+                Ingredient::Item(item) => self.by_input.entry(item.id.clone()).or_default(),
+                Ingredient::Tag(tag) => self.by_tag.entry(tag.clone()).or_default(),
+            }
+            .push(i);
+        }
+        self.recipes.push(recipe);
+    }
+
+    /// Finds a shapeless recipe of the `workbench` that can be crafted from the `layout`.
+    /// The order of the items doesn't matter and the layout may contain more items
+    /// than the recipe needs, but every item kind must be used by the recipe.
+    /// Tags are read from the offered items, so the items of a client are
+    /// [resolved](CraftInput::resolve) first.
+    pub fn find(
+        &self,
+        workbench: AssetId<Workbench>,
+        layout: &ItemsLayout,
+    ) -> Option<RecipeMatch<'_>> {
+        let mut keys = merge(layout.get())
+            .into_iter()
+            .map(|(item, _)| item.id.clone())
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+
+        self.by_ingredients
+            .get(&keys)
+            .into_iter()
+            .chain(self.tagged.get(&workbench))
+            .flatten()
+            .map(|&i| &self.recipes[i])
+            .filter(|recipe| recipe.workbench == workbench)
+            .find_map(|recipe| {
+                Some(RecipeMatch {
+                    recipe,
+                    consumed: recipe.consume(layout.get())?,
+                })
+            })
+    }
+
+    /// Finds a shaped recipe of the `workbench` whose pattern or its mirror image
+    /// is placed in the `grid`. Empty rows and columns around the items are ignored.
+    /// The items must be resolved like for [`RecipeIndex::find`].
+    pub fn find_shaped(
+        &self,
+        workbench: AssetId<Workbench>,
+        grid: &CraftingGrid,
+    ) -> Option<RecipeMatch<'_>> {
+        let shape = grid.shape();
+        if shape.is_empty() {
+            return None;
+        }
+        let recipe = self
+            .by_shape
+            .get(&shape.size())?
+            .iter()
+            .map(|&i| &self.recipes[i])
+            .filter(|recipe| recipe.workbench == workbench)
+            .find(|recipe| {
+                recipe
+                    .shape
+                    .as_ref()
+                    .is_some_and(|s| s.matches(&shape, Ingredient::accepts))
+            })?;
+
+        let cells = grid
+            .items()
+            .map(|bundle| ItemBundle {
+                item: bundle.item.clone(),
+                stack: ItemStack(1),
+            })
+            .collect::<Vec<_>>();
+        let consumed = merge(&cells)
+            .into_iter()
+            .map(|(item, amount)| {
+                Some(ItemBundle {
+                    item: item.clone(),
+                    stack: ItemStack(u8::try_from(amount).ok()?),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(RecipeMatch { recipe, consumed })
+    }
+
+    pub fn recipes(&self) -> &[Recipe] {
+        &self.recipes
+    }
+
+    /// Shapeless and shaped recipes of the `workbench`.
+    pub fn workbench_recipes(
+        &self,
+        workbench: AssetId<Workbench>,
+    ) -> impl Iterator<Item = &Recipe> {
+        self.by_workbench
+            .get(&workbench)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.recipes[i])
+    }
+
+    /// Recipes with the `item` in their output.
+    pub fn producing(&self, item: &Item) -> impl Iterator<Item = &Recipe> {
+        self.by_output
+            .get(&item.id)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.recipes[i])
+    }
+
+    /// Recipes that accept the `item` as an ingredient, directly or by one of its tags.
+    pub fn consuming(&self, item: &Item) -> impl Iterator<Item = &Recipe> {
+        let mut indices = self.by_input.get(&item.id).cloned().unwrap_or_default();
+        for tag in &item.tags {
+            for &i in self.by_tag.get(tag).into_iter().flatten() {
+                push_unique(&mut indices, i);
+            }
+        }
+        indices.sort_unstable();
+        indices.into_iter().map(|i| &self.recipes[i])
+    }
+
+    /// Recipes that can be crafted from the `available` items, e.g. the items of an
+    /// [`Inventory`](super::Inventory), with the input to request each of them.
+    pub fn craftable<'a>(
+        &'a self,
+        available: &'a [(&Item, &ItemStack)],
+    ) -> impl Iterator<Item = (&'a Recipe, CraftInput)> + 'a {
+        self.recipes
+            .iter()
+            .filter_map(|recipe| Some((recipe, recipe.offer(available)?)))
+    }
+
+    pub fn clear(&mut self) {
+        self.recipes.clear();
+        self.by_ingredients.clear();
+        self.tagged.clear();
+        self.by_shape.clear();
+        self.by_workbench.clear();
+        self.by_output.clear();
+        self.by_input.clear();
+        self.by_tag.clear();
+    }
+}
+
+/// Recipes the player has discovered. Only unlocked recipes can be crafted.
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UnlockedRecipes(pub HashSet<RecipeId>);
+
+impl UnlockedRecipes {
+    pub fn contains(&self, recipe: &Recipe) -> bool {
+        self.0.contains(&recipe.id)
+    }
+
+    /// Returns `true` if the recipe was not unlocked before.
+    pub fn unlock(&mut self, recipe: &Recipe) -> bool {
+        self.0.insert(recipe.id)
+    }
+}
+
+fn resolve_ingredient(
+    ingredient: &IngredientRef,
+    item_assets: &Assets<Item>,
+) -> Option<Ingredient> {
+    match ingredient {
+        IngredientRef::Item(item) => item
+            .get_handle()
+            .and_then(|h| item_assets.get(h))
+            .map(|item| Ingredient::Item(item.clone())),
+        IngredientRef::Tag(tag) => Some(Ingredient::Tag(tag.clone())),
+    }
+}
+
+/// Resolves the ingredients of the `recipe` pattern.
+/// Returns `None` if a key is missing or its item is not loaded.
+fn resolve_pattern(recipe: &ShapedRecipe, item_assets: &Assets<Item>) -> Option<Shape<Ingredient>> {
+    let width = recipe.pattern.iter().map(|row| row.chars().count()).max()?;
+    let mut cells = Vec::with_capacity(width * recipe.pattern.len());
+    for row in &recipe.pattern {
+        let mut symbols = row.chars();
+        for _ in 0..width {
+            cells.push(match symbols.next() {
+                None | Some(' ') => None,
+                Some(symbol) => Some(resolve_ingredient(recipe.key.get(&symbol)?, item_assets)?),
+            });
+        }
+    }
+    Some(Shape::new(width, cells))
+}
+
+/// Merges the stacks of the same item kind.
+fn merge(items: &[ItemBundle]) -> Vec<(&Item, u32)> {
+    let mut merged = Vec::<(&Item, u32)>::new();
+    for ItemBundle { item, stack } in items {
+        match merged.iter_mut().find(|(i, _)| i.is_same_kind(item)) {
+            Some((_, amount)) => *amount += stack.0 as u32,
+            None => merged.push((item, stack.0 as u32)),
+        }
+    }
+    merged
+}
+
+/// Distributes the `amounts` of items between ingredients with the `required` amounts,
+/// an item can be split between several ingredients that accept it.
+/// Ingredients are tried in the `order`. Returns how much of every item goes to every
+/// ingredient, or `None` if some ingredient can't get enough items.
+fn distribute(
+    amounts: &[u32],
+    required: &[u32],
+    order: &[usize],
+    accepts: impl Fn(usize, usize) -> bool,
+) -> Option<Vec<Vec<u32>>> {
+    let mut flow = vec![vec![0; required.len()]; amounts.len()];
+    let mut missing = required.to_vec();
+    for (item, &amount) in amounts.iter().enumerate() {
+        let mut left = amount;
+        while left > 0 {
+            let mut visited = vec![false; required.len()];
+            let mut path = Vec::new();
+            if !find_path(
+                item,
+                &flow,
+                &missing,
+                order,
+                &accepts,
+                &mut visited,
+                &mut path,
+            ) {
+                break;
+            }
+            // The path alternates between giving an item to an ingredient
+            // and moving another item away from that ingredient.
+            let Some(&(_, last)) = path.last() else {
+                break;
+            };
+            let moved = path
+                .iter()
+                .skip(1)
+                .zip(&path)
+                .map(|((item, _), (_, ingredient))| flow[*item][*ingredient])
+                .fold(left.min(missing[last]), u32::min);
+            for (k, &(item, ingredient)) in path.iter().enumerate() {
+                flow[item][ingredient] += moved;
+                if k > 0 {
+                    flow[item][path[k - 1].1] -= moved;
+                }
+            }
+            missing[last] -= moved;
+            left -= moved;
+        }
+    }
+    missing.iter().all(|m| *m == 0).then_some(flow)
+}
+
+/// Finds ingredients for the `item` leading to an ingredient that still misses items.
+/// An ingredient that is already full can be used if an item given to it can move on.
+fn find_path(
+    item: usize,
+    flow: &[Vec<u32>],
+    missing: &[u32],
+    order: &[usize],
+    accepts: &impl Fn(usize, usize) -> bool,
+    visited: &mut [bool],
+    path: &mut Vec<(usize, usize)>,
+) -> bool {
+    for &ingredient in order {
+        if visited[ingredient] || !accepts(item, ingredient) {
+            continue;
+        }
+        visited[ingredient] = true;
+        path.push((item, ingredient));
+        if missing[ingredient] > 0 {
+            return true;
+        }
+        for other in 0..flow.len() {
+            if other != item
+                && flow[other][ingredient] > 0
+                && find_path(other, flow, missing, order, accepts, visited, path)
+            {
+                return true;
+            }
+        }
+        path.pop();
+    }
+    false
+}
+
+fn push_unique(indices: &mut Vec<usize>, i: usize) {
+    if !indices.contains(&i) {
+        indices.push(i);
+    }
+}
+
+fn merge_ingredients(input: Vec<(Ingredient, ItemStack)>) -> Vec<(Ingredient, ItemStack)> {
+    let mut merged = Vec::<(Ingredient, ItemStack)>::new();
+    for (ingredient, stack) in input {
+        match merged.iter_mut().find(|(i, _)| *i == ingredient) {
+            Some((_, amount)) => amount.0 = amount.0.saturating_add(stack.0),
+            None => merged.push((ingredient, stack)),
+        }
+    }
+    merged
+}
+
+pub(super) fn build_recipe_index(
+    mut index: ResMut<RecipeIndex>,
+    workbenches: Res<Assets<Workbench>>,
+    item_assets: Res<Assets<Item>>,
+) {
+    index.clear();
+    for (id, workbench) in workbenches.iter() {
+        index.add_workbench(id, workbench, &item_assets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::AssetId;
+
+    use super::{Ingredient, RecipeIndex};
+    use crate::plugins::crafting::logic::{
+        test_item, CraftInput, CraftingGrid, Item, ItemBundle, ItemStack, Layout, Shape,
+    };
+
+    fn bundle(name: &str, amount: u8) -> ItemBundle {
+        ItemBundle {
+            item: test_item(name),
+            stack: ItemStack(amount),
+        }
+    }
+
+    #[test]
+    fn find_ignores_order_and_accepts_more() {
+        let mut index = RecipeIndex::default();
+        let workbench = AssetId::default();
+        index.insert(
+            workbench,
+            "Workbench",
+            vec![
+                (Ingredient::Item(test_item("Wood")), ItemStack(2)),
+                (Ingredient::Item(test_item("Stone")), ItemStack(1)),
+            ],
+            vec![bundle("Axe", 1)],
+            0.0,
+        );
+
+        let layout = Layout(vec![
+            bundle("Stone", 3),
+            bundle("Wood", 1),
+            bundle("Wood", 1),
+        ]);
+        let found = index.find(workbench, &layout).unwrap();
+        assert_eq!(found.recipe.output, vec![bundle("Axe", 1)]);
+        assert_eq!(found.consumed, vec![bundle("Stone", 1), bundle("Wood", 2)]);
+
+        let layout = Layout(vec![bundle("Stone", 1), bundle("Wood", 1)]);
+        assert!(index.find(workbench, &layout).is_none());
+
+        let layout = Layout(vec![
+            bundle("Stone", 1),
+            bundle("Wood", 2),
+            bundle("Axe", 1),
+        ]);
+        assert!(index.find(workbench, &layout).is_none());
+    }
+
+    #[test]
+    fn find_accepts_any_item_with_tag() {
+        let mut index = RecipeIndex::default();
+        let workbench = AssetId::default();
+        index.insert(
+            workbench,
+            "Workbench",
+            vec![(Ingredient::Tag("wood".to_string()), ItemStack(3))],
+            vec![bundle("Stick", 1)],
+            0.0,
+        );
+
+        let wood = |name: &str, amount| ItemBundle {
+            item: Item {
+                tags: vec!["wood".to_string()],
+                ..test_item(name)
+            },
+            stack: ItemStack(amount),
+        };
+        let layout = Layout(vec![wood("Oak", 2), wood("Birch", 2)]);
+        let found = index.find(workbench, &layout).unwrap();
+        assert_eq!(found.consumed, vec![wood("Oak", 2), wood("Birch", 1)]);
+
+        let layout = Layout(vec![wood("Oak", 3), bundle("Stone", 1)]);
+        assert!(index.find(workbench, &layout).is_none());
+    }
+
+    #[test]
+    fn surplus_of_an_item_goes_to_tag_ingredients() {
+        let mut index = RecipeIndex::default();
+        let workbench = AssetId::default();
+        let oak = Item {
+            tags: vec!["wood".to_string(), "log".to_string()],
+            ..test_item("Oak")
+        };
+        index.insert(
+            workbench,
+            "Workbench",
+            vec![
+                (Ingredient::Item(oak.clone()), ItemStack(1)),
+                (Ingredient::Tag("wood".to_string()), ItemStack(2)),
+            ],
+            vec![bundle("Chair", 1)],
+            0.0,
+        );
+        index.insert(
+            workbench,
+            "Workbench",
+            vec![
+                (Ingredient::Tag("wood".to_string()), ItemStack(1)),
+                (Ingredient::Tag("log".to_string()), ItemStack(2)),
+            ],
+            vec![bundle("Table", 1)],
+            0.0,
+        );
+        let oak = |amount| ItemBundle {
+            item: oak.clone(),
+            stack: ItemStack(amount),
+        };
+
+        let found = index.find(workbench, &Layout(vec![oak(3)])).unwrap();
+        assert_eq!(found.recipe.output, vec![bundle("Chair", 1)]);
+        assert_eq!(found.consumed, vec![oak(3)]);
+        assert!(index.find(workbench, &Layout(vec![oak(2)])).is_none());
+
+        let table = &index.recipes()[1];
+        let birch = Item {
+            tags: vec!["wood".to_string()],
+            ..test_item("Birch")
+        };
+        let birch = ItemBundle {
+            item: birch,
+            stack: ItemStack(1),
+        };
+        // Oak has to leave the "wood" tag to Birch to fill the "log" tag.
+        let offered = [oak(2), birch.clone()];
+        assert_eq!(table.consume(&offered), Some(vec![oak(2), birch]));
+    }
+
+    #[test]
+    fn find_shaped_accepts_mirror_and_offset() {
+        let mut index = RecipeIndex::default();
+        let workbench = AssetId::default();
+        let wood = Some(Ingredient::Item(test_item("Wood")));
+        let stone = Some(Ingredient::Item(test_item("Stone")));
+        // WW
+        //  S
+        let pattern = Shape::new(2, vec![wood.clone(), wood, None, stone]);
+        index.insert_shaped(workbench, "Workbench", pattern, vec![bundle("Axe", 1)], 0.0);
+
+        let mut grid = CraftingGrid::new(3, 3);
+        grid.set(1, 1, Some(bundle("Wood", 5)));
+        grid.set(2, 1, Some(bundle("Wood", 1)));
+        grid.set(2, 2, Some(bundle("Stone", 1)));
+        let found = index.find_shaped(workbench, &grid).unwrap();
+        assert_eq!(found.consumed, vec![bundle("Wood", 2), bundle("Stone", 1)]);
+
+        grid.set(2, 2, None);
+        grid.set(1, 2, Some(bundle("Stone", 1)));
+        assert!(index.find_shaped(workbench, &grid).is_some());
+
+        grid.set(0, 0, Some(bundle("Stone", 1)));
+        assert!(index.find_shaped(workbench, &grid).is_none());
+    }
+
+    #[test]
+    fn offer_spreads_tag_cells_across_items() {
+        let mut index = RecipeIndex::default();
+        let workbench = AssetId::default();
+        let wood = Some(Ingredient::Tag("wood".to_string()));
+        // WWW
+        let pattern = Shape::new(3, vec![wood.clone(), wood.clone(), wood]);
+        index.insert_shaped(
+            workbench,
+            "Workbench",
+            pattern,
+            vec![bundle("Shelf", 1)],
+            0.0,
+        );
+        let wood = |name: &str| Item {
+            tags: vec!["wood".to_string()],
+            ..test_item(name)
+        };
+        let (oak, birch) = (wood("Oak"), wood("Birch"));
+
+        let recipe = &index.recipes()[0];
+        let (two, one) = (ItemStack(2), ItemStack(1));
+        let Some(CraftInput::Shaped(grid)) = recipe.offer(&[(&oak, &two), (&birch, &one)]) else {
+            panic!("the items fill every cell");
+        };
+        assert_eq!(grid.items().filter(|bundle| bundle.item == oak).count(), 2);
+        assert_eq!(
+            grid.items().filter(|bundle| bundle.item == birch).count(),
+            1
+        );
+        assert!(index.find_shaped(workbench, &grid).is_some());
+
+        assert!(recipe.offer(&[(&oak, &one), (&birch, &one)]).is_none());
+    }
+
+    #[test]
+    fn queries_by_output_and_ingredient() {
+        let mut index = RecipeIndex::default();
+        let workbench = AssetId::default();
+        index.insert(
+            workbench,
+            "Workbench",
+            vec![(Ingredient::Item(test_item("Wood")), ItemStack(2))],
+            vec![bundle("Stick", 4)],
+            0.0,
+        );
+        index.insert(
+            workbench,
+            "Workbench",
+            vec![(Ingredient::Tag("wood".to_string()), ItemStack(1))],
+            vec![bundle("Plank", 1)],
+            0.0,
+        );
+        let wood = Item {
+            tags: vec!["wood".to_string()],
+            ..test_item("Wood")
+        };
+
+        let producing = index.producing(&test_item("Stick")).collect::<Vec<_>>();
+        assert_eq!(producing.len(), 1);
+        assert_eq!(index.consuming(&wood).count(), 2);
+        assert_eq!(index.consuming(&test_item("Wood")).count(), 1);
+
+        let stack = ItemStack(1);
+        let available = [(&wood, &stack)];
+        let craftable = index.craftable(&available).collect::<Vec<_>>();
+        assert_eq!(craftable.len(), 1);
+        assert_eq!(craftable[0].0.output, vec![bundle("Plank", 1)]);
+        assert_ne!(index.recipes()[0].id, index.recipes()[1].id);
+    }
+
+    #[test]
+    fn recipe_ids_are_stable_and_include_the_workbench() {
+        let mut index = RecipeIndex::default();
+        for name in ["Workbench", "Furnace"] {
+            index.insert(
+                AssetId::default(),
+                name,
+                vec![(Ingredient::Item(test_item("Wood")), ItemStack(2))],
+                vec![bundle("Stick", 4)],
+                0.0,
+            );
+        }
+
+        // Changing these breaks the recipes unlocked by players.
+        let ids = index
+            .recipes()
+            .iter()
+            .map(|recipe| recipe.id.0)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![9055388338824807496, 17494783730079835583]);
+    }
+}
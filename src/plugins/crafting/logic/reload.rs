@@ -0,0 +1,155 @@
+use bevy::{
+    app::{Plugin, Update},
+    asset::{AssetEvent, AssetId, AssetServer, Assets},
+    ecs::{
+        event::{Event, EventReader, EventWriter},
+        schedule::{
+            common_conditions::{in_state, not, on_event},
+            Condition, IntoSystemConfigs,
+        },
+        system::{Local, Res, ResMut},
+    },
+    log::{info, warn},
+    utils::hashbrown::HashMap,
+};
+
+use crate::GameState;
+
+use super::{
+    build_item_registry, build_recipe_index, check_recipe_graph, Item, RecipeIndex, Workbench,
+};
+
+/// Applies changes of the item and workbench assets while the game is running.
+/// Assets are watched for changes with the `hot_reload` feature.
+///
+/// Item entities reference their definitions by [`ItemId`](super::ItemId),
+/// so they pick up the changes without being updated.
+pub struct HotReloadPlugin;
+
+impl Plugin for HotReloadPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_event::<ItemDefinitionChanged>().add_systems(
+            Update,
+            (
+                (track_item_definitions, log_workbench_changes),
+                build_item_registry.run_if(on_event::<ItemDefinitionChanged>()),
+                (build_recipe_index, check_recipe_graph).chain().run_if(
+                    on_event::<ItemDefinitionChanged>()
+                        .or_else(on_event::<AssetEvent<Workbench>>()),
+                ),
+            )
+                .chain()
+                .run_if(not(in_state(GameState::Loading))),
+        );
+    }
+}
+
+/// An [`Item`] asset was modified.
+#[derive(Event, Debug, Clone)]
+pub struct ItemDefinitionChanged {
+    pub id: AssetId<Item>,
+    pub old: Item,
+    pub new: Item,
+}
+
+/// Remembers the item definitions to tell what changed when an item asset is modified.
+///
+/// Item entities, recipes and other assets refer to items by their [`ItemId`](super::ItemId),
+/// so a changed id is reverted to the old one.
+fn track_item_definitions(
+    mut events: EventReader<AssetEvent<Item>>,
+    mut definitions: Local<HashMap<AssetId<Item>, Item>>,
+    mut item_assets: ResMut<Assets<Item>>,
+    asset_server: Res<AssetServer>,
+    mut changed: EventWriter<ItemDefinitionChanged>,
+) {
+    if definitions.is_empty() {
+        definitions.extend(item_assets.iter().map(|(id, item)| (id, item.clone())));
+    }
+
+    for event in events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        let path = asset_server
+            .get_path(*id)
+            .map_or_else(|| format!("{id:?}"), |path| path.to_string());
+        let changed_id = definitions
+            .get(id)
+            .map(|old| &old.id)
+            .filter(|old_id| item_assets.get(*id).is_some_and(|new| new.id != **old_id));
+        // `get_mut` marks the asset as modified, so it's only called for a changed id
+        if let Some(old_id) = changed_id {
+            if let Some(new) = item_assets.get_mut(*id) {
+                warn!(
+                    "Item {path} changed its id {:?} -> {:?}, the old id is kept. \
+                    Restart the game to apply the new one",
+                    old_id.0, new.id.0
+                );
+                new.id = old_id.clone();
+            }
+        }
+
+        let Some(new) = item_assets.get(*id) else {
+            continue;
+        };
+        let Some(old) = definitions.insert(*id, new.clone()) else {
+            continue;
+        };
+        let changes = describe_changes(&old, new);
+        if changes.is_empty() {
+            continue;
+        }
+
+        info!("Item {path} reloaded: {}", changes.join(", "));
+        changed.send(ItemDefinitionChanged {
+            id: *id,
+            old,
+            new: new.clone(),
+        });
+    }
+}
+
+fn describe_changes(old: &Item, new: &Item) -> Vec<String> {
+    let mut changes = Vec::new();
+    if old.name != new.name {
+        changes.push(format!("name {:?} -> {:?}", old.name, new.name));
+    }
+    if old.kind != new.kind {
+        changes.push(format!("kind {:?} -> {:?}", old.kind, new.kind));
+    }
+    if old.level != new.level {
+        changes.push(format!("level {} -> {}", old.level, new.level));
+    }
+    if old.max_stack != new.max_stack {
+        changes.push(format!("max_stack {} -> {}", old.max_stack, new.max_stack));
+    }
+    if old.tags != new.tags {
+        changes.push(format!("tags {:?} -> {:?}", old.tags, new.tags));
+    }
+    if old.equip != new.equip {
+        changes.push(format!("equip {:?} -> {:?}", old.equip, new.equip));
+    }
+    changes
+}
+
+fn log_workbench_changes(
+    mut events: EventReader<AssetEvent<Workbench>>,
+    workbenches: Res<Assets<Workbench>>,
+    recipes: Res<RecipeIndex>,
+) {
+    for event in events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        let Some(workbench) = workbenches.get(*id) else {
+            continue;
+        };
+        info!(
+            "Workbench {:?} reloaded: {} -> {} recipes",
+            workbench.name(),
+            recipes.workbench_recipes(*id).count(),
+            workbench.recipes().len() + workbench.shaped().len()
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::{Entity, MapEntities},
+        event::Event,
+    },
+    reflect::{std_traits::ReflectDefault, Reflect},
+};
+use serde::{Deserialize, Serialize};
+
+use super::ItemBundle;
+
+/// A [`Workbench`](super::Workbench) placed in the world. Crafting happens at stations.
+#[derive(Component, Debug, Clone, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub struct WorkbenchStation {
+    /// Asset path of the [`Workbench`](super::Workbench),
+    /// e.g. `workbenches/classical.workbench.ron`.
+    pub workbench: String,
+}
+
+impl WorkbenchStation {
+    /// Capacity of the station's [`Inventory`](super::Inventory), it keeps crafted items
+    /// that didn't fit into the player's inventory.
+    pub const OUTPUT_CAPACITY: usize = 9;
+}
+
+/// Crafting jobs of a [`WorkbenchStation`]. Only the first job progresses.
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CraftingQueue {
+    pub jobs: VecDeque<CraftJob>,
+    next_id: u32,
+}
+
+impl CraftingQueue {
+    /// Adds a job to the end of the queue and returns its id.
+    pub fn push(
+        &mut self,
+        owner: Entity,
+        consumed: Vec<ItemBundle>,
+        output: Vec<ItemBundle>,
+        duration: f32,
+    ) -> u32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.jobs.push_back(CraftJob {
+            id,
+            owner,
+            consumed,
+            output,
+            duration,
+            elapsed: 0.0,
+        });
+        id
+    }
+
+    /// Removes the job of the `owner`.
+    pub fn cancel(&mut self, owner: Entity, id: u32) -> Option<CraftJob> {
+        let index = self
+            .jobs
+            .iter()
+            .position(|job| job.id == id && job.owner == owner)?;
+        self.jobs.remove(index)
+    }
+}
+
+impl MapEntities for CraftingQueue {
+    fn map_entities<M: bevy::prelude::EntityMapper>(&mut self, entity_mapper: &mut M) {
+        for job in self.jobs.iter_mut() {
+            job.owner = entity_mapper.map_entity(job.owner);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CraftJob {
+    pub id: u32,
+    /// Player who requested the job.
+    pub owner: Entity,
+    /// Items taken from the owner's inventory, they are refunded on cancellation.
+    pub consumed: Vec<ItemBundle>,
+    pub output: Vec<ItemBundle>,
+    /// Crafting time in seconds.
+    pub duration: f32,
+    pub elapsed: f32,
+}
+
+impl CraftJob {
+    /// Progress from `0.0` to `1.0`.
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        (self.elapsed / self.duration).min(1.0)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+/// Request to cancel the own job of the `station`. The ingredients are refunded.
+#[derive(Debug, Clone, Event, Serialize, Deserialize)]
+pub struct CancelCraft {
+    pub station: Entity,
+    pub job: u32,
+}
+
+impl MapEntities for CancelCraft {
+    fn map_entities<M: bevy::prelude::EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.station = entity_mapper.map_entity(self.station);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::entity::Entity;
+
+    use super::CraftingQueue;
+
+    #[test]
+    fn only_owner_cancels_job() {
+        let owner = Entity::from_raw(1);
+        let mut queue = CraftingQueue::default();
+        let first = queue.push(owner, Vec::new(), Vec::new(), 2.0);
+        let second = queue.push(owner, Vec::new(), Vec::new(), 0.0);
+        assert_ne!(first, second);
+        assert_eq!(queue.jobs[0].progress(), 0.0);
+        assert_eq!(queue.jobs[1].progress(), 1.0);
+
+        assert!(queue.cancel(Entity::from_raw(2), first).is_none());
+        assert_eq!(queue.cancel(owner, first).map(|job| job.id), Some(first));
+        assert_eq!(queue.jobs.len(), 1);
+    }
+}
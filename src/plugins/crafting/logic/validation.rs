@@ -0,0 +1,359 @@
+use std::fmt;
+
+use bevy::{
+    asset::{Asset, AssetPath, Assets, UntypedAssetId},
+    utils::hashbrown::HashMap,
+};
+
+use crate::asset_ref::AssetRef;
+
+use super::{EnchantmentDefinition, IngredientRef, Item, ItemStack, Loot, LootTable, Workbench};
+
+/// Mistake in the item, workbench, enchantment or loot table assets found by [`validate_assets`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetError {
+    /// Some assets failed to load, the reason is logged by the asset server.
+    LoadingFailed,
+    /// An item path of the workbench doesn't point to a loaded item.
+    UnresolvedItem { workbench: String, path: String },
+    /// Several item assets have the same [`ItemId`](super::ItemId).
+    DuplicateItemId { id: String, paths: Vec<String> },
+    /// Several item assets have the same name.
+    DuplicateItemName { name: String, paths: Vec<String> },
+    /// A recipe without ingredients or without output.
+    EmptyRecipe { workbench: String },
+    /// A recipe stack with no items.
+    ZeroQuantity { workbench: String, item: String },
+    /// A symbol of a shaped recipe pattern that is missing in its key.
+    UnknownKey { workbench: String, symbol: char },
+    /// An item path of the enchantment cost doesn't point to a loaded item.
+    UnresolvedCost { enchantment: String, path: String },
+    /// An item or nested table path of the loot table doesn't point to a loaded asset.
+    UnresolvedLoot { table: String, path: String },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::LoadingFailed => write!(f, "some assets failed to load"),
+            AssetError::UnresolvedItem { workbench, path } => {
+                write!(f, "workbench {workbench:?}: item {path:?} is not loaded")
+            }
+            AssetError::DuplicateItemId { id, paths } => {
+                write!(f, "item id {id:?} is used by {}", paths.join(", "))
+            }
+            AssetError::DuplicateItemName { name, paths } => {
+                write!(f, "item name {name:?} is used by {}", paths.join(", "))
+            }
+            AssetError::EmptyRecipe { workbench } => {
+                write!(f, "workbench {workbench:?}: recipe without input or output")
+            }
+            AssetError::ZeroQuantity { workbench, item } => {
+                write!(f, "workbench {workbench:?}: zero quantity of {item}")
+            }
+            AssetError::UnknownKey { workbench, symbol } => {
+                write!(
+                    f,
+                    "workbench {workbench:?}: pattern symbol {symbol:?} is not in the key"
+                )
+            }
+            AssetError::UnresolvedCost { enchantment, path } => {
+                write!(
+                    f,
+                    "enchantment {enchantment:?}: cost item {path:?} is not loaded"
+                )
+            }
+            AssetError::UnresolvedLoot { table, path } => {
+                write!(f, "loot table {table:?}: {path:?} is not loaded")
+            }
+        }
+    }
+}
+
+/// Checks the loaded items, workbenches, enchantments and loot tables. Returns all found mistakes.
+/// `asset_path` names item and loot table assets in the errors.
+pub fn validate_assets<'a>(
+    item_assets: &Assets<Item>,
+    workbenches: impl IntoIterator<Item = &'a Workbench>,
+    enchantments: impl IntoIterator<Item = &'a EnchantmentDefinition>,
+    loot_tables: &Assets<LootTable>,
+    asset_path: impl Fn(UntypedAssetId) -> Option<String>,
+) -> Vec<AssetError> {
+    let mut errors = Vec::new();
+
+    errors.extend(duplicates(
+        item_assets,
+        &asset_path,
+        |item| &item.id.0,
+        |id, paths| AssetError::DuplicateItemId { id, paths },
+    ));
+    errors.extend(duplicates(
+        item_assets,
+        &asset_path,
+        |item| &item.name,
+        |name, paths| AssetError::DuplicateItemName { name, paths },
+    ));
+
+    for workbench in workbenches {
+        let name = workbench.name();
+        for (input, output) in workbench.recipes() {
+            for (item, stack) in &output.items {
+                check_item(&mut errors, name, item_assets, item, Some(stack));
+            }
+            for (ingredient, stack) in input {
+                match ingredient {
+                    IngredientRef::Item(item) => {
+                        check_item(&mut errors, name, item_assets, item, Some(stack))
+                    }
+                    IngredientRef::Tag(tag) if stack.0 == 0 => {
+                        errors.push(AssetError::ZeroQuantity {
+                            workbench: name.to_string(),
+                            item: format!("tag {tag:?}"),
+                        })
+                    }
+                    IngredientRef::Tag(_) => {}
+                }
+            }
+            if input.is_empty() || output.items.is_empty() {
+                errors.push(AssetError::EmptyRecipe {
+                    workbench: name.to_string(),
+                });
+            }
+        }
+
+        for recipe in workbench.shaped() {
+            for (item, stack) in &recipe.output {
+                check_item(&mut errors, name, item_assets, item, Some(stack));
+            }
+            for ingredient in recipe.key.values() {
+                if let IngredientRef::Item(item) = ingredient {
+                    check_item(&mut errors, name, item_assets, item, None);
+                }
+            }
+            let mut symbols = recipe
+                .pattern
+                .iter()
+                .flat_map(|row| row.chars())
+                .filter(|symbol| *symbol != ' ')
+                .peekable();
+            if symbols.peek().is_none() || recipe.output.is_empty() {
+                errors.push(AssetError::EmptyRecipe {
+                    workbench: name.to_string(),
+                });
+            }
+            for symbol in symbols {
+                if !recipe.key.contains_key(&symbol)
+                    && !errors.contains(&AssetError::UnknownKey {
+                        workbench: name.to_string(),
+                        symbol,
+                    })
+                {
+                    errors.push(AssetError::UnknownKey {
+                        workbench: name.to_string(),
+                        symbol,
+                    });
+                }
+            }
+        }
+    }
+
+    for definition in enchantments {
+        for (item, _) in &definition.cost {
+            if item.get_handle().and_then(|h| item_assets.get(h)).is_none() {
+                errors.push(AssetError::UnresolvedCost {
+                    enchantment: definition.enchantment.name.clone(),
+                    path: ref_path(item),
+                });
+            }
+        }
+    }
+
+    let mut tables = loot_tables
+        .iter()
+        .map(|(id, table)| {
+            let name = asset_path(id.untyped()).unwrap_or_else(|| format!("{id:?}"));
+            (name, table)
+        })
+        .collect::<Vec<_>>();
+    tables.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    for (name, table) in tables {
+        for entry in &table.entries {
+            let path = match &entry.loot {
+                Loot::Item { item, .. } => item
+                    .get_handle()
+                    .and_then(|h| item_assets.get(h))
+                    .is_none()
+                    .then(|| ref_path(item)),
+                Loot::Table(nested) => nested
+                    .get_handle()
+                    .and_then(|h| loot_tables.get(h))
+                    .is_none()
+                    .then(|| ref_path(nested)),
+                Loot::Nothing => None,
+            };
+            if let Some(path) = path {
+                errors.push(AssetError::UnresolvedLoot {
+                    table: name.clone(),
+                    path,
+                });
+            }
+        }
+    }
+
+    errors
+}
+
+/// Groups the items by the `key` and reports the keys used by several items.
+fn duplicates(
+    item_assets: &Assets<Item>,
+    asset_path: &impl Fn(UntypedAssetId) -> Option<String>,
+    key: impl Fn(&Item) -> &String,
+    error: impl Fn(String, Vec<String>) -> AssetError,
+) -> Vec<AssetError> {
+    let mut groups = HashMap::<&str, Vec<String>>::new();
+    for (id, item) in item_assets.iter() {
+        groups
+            .entry(key(item))
+            .or_default()
+            .push(asset_path(id.untyped()).unwrap_or_else(|| format!("{id:?}")));
+    }
+    let mut duplicates = groups
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|(key, mut paths)| {
+            paths.sort_unstable();
+            error(key.to_string(), paths)
+        })
+        .collect::<Vec<_>>();
+    duplicates.sort_unstable_by_key(|err| err.to_string());
+    duplicates
+}
+
+/// Checks that the `item` is loaded and its `stack` is not empty.
+fn check_item(
+    errors: &mut Vec<AssetError>,
+    workbench: &str,
+    item_assets: &Assets<Item>,
+    item: &AssetRef<Item>,
+    stack: Option<&ItemStack>,
+) {
+    let loaded = item.get_handle().and_then(|handle| item_assets.get(handle));
+    let path = ref_path(item);
+    if loaded.is_none() {
+        errors.push(AssetError::UnresolvedItem {
+            workbench: workbench.to_string(),
+            path: path.clone(),
+        });
+    }
+    if stack.is_some_and(|stack| stack.0 == 0) {
+        errors.push(AssetError::ZeroQuantity {
+            workbench: workbench.to_string(),
+            item: loaded.map_or(path, |item| format!("{:?}", item.name)),
+        });
+    }
+}
+
+/// Path of the asset for error messages, loaded handles keep the path they were loaded from.
+fn ref_path<A: Asset>(asset: &AssetRef<A>) -> String {
+    match asset {
+        AssetRef::Handle(handle) => handle
+            .path()
+            .map(AssetPath::to_string)
+            .unwrap_or_else(|| format!("{:?}", handle.id())),
+        AssetRef::Path(path) => path.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::Assets;
+
+    use super::{validate_assets, AssetError};
+    use crate::plugins::crafting::logic::{
+        EnchantmentDefinition, Item, ItemId, LootTable, Workbench,
+    };
+
+    #[test]
+    fn reports_every_mistake() {
+        let workbench = ron::from_str::<Workbench>(
+            r#"(
+                name: "Broken",
+                recipes: {
+                    [(Tag("wood"), ItemStack(0))]: (items: []),
+                },
+                shaped: [(pattern: ["ab"], key: {'a': Path("items/typo.item.ron")}, output: [])],
+            )"#,
+        )
+        .unwrap();
+        let mut items = Assets::<Item>::default();
+        for id in ["a", "b"] {
+            items.add(Item {
+                id: ItemId(id.to_string()),
+                ..Default::default()
+            });
+        }
+
+        let enchantment = ron::from_str::<EnchantmentDefinition>(
+            r#"(
+                enchantment: (name: "Power", level: 1, stats: ()),
+                cost: [(Path("items/gem.item.ron"), ItemStack(1))],
+            )"#,
+        )
+        .unwrap();
+
+        let mut tables = Assets::<LootTable>::default();
+        let table = ron::from_str::<LootTable>(
+            r#"(entries: [
+                (loot: Item(item: Path("items/coin.item.ron"))),
+                (loot: Table(Path("loot_tables/rare.loot.ron"))),
+                (loot: Nothing),
+            ])"#,
+        )
+        .unwrap();
+        let table = tables.add(table).id().untyped();
+
+        let errors = validate_assets(&items, [&workbench], [&enchantment], &tables, |id| {
+            (id == table).then(|| "loot_tables/broken.loot.ron".to_string())
+        });
+        let broken = || "Broken".to_string();
+        assert!(matches!(
+            &errors[0],
+            AssetError::DuplicateItemName { paths, .. } if paths.len() == 2
+        ));
+        assert_eq!(
+            errors[1..],
+            [
+                AssetError::ZeroQuantity {
+                    workbench: broken(),
+                    item: "tag \"wood\"".to_string(),
+                },
+                AssetError::EmptyRecipe {
+                    workbench: broken()
+                },
+                AssetError::UnresolvedItem {
+                    workbench: broken(),
+                    path: "items/typo.item.ron".to_string(),
+                },
+                AssetError::EmptyRecipe {
+                    workbench: broken()
+                },
+                AssetError::UnknownKey {
+                    workbench: broken(),
+                    symbol: 'b',
+                },
+                AssetError::UnresolvedCost {
+                    enchantment: "Power".to_string(),
+                    path: "items/gem.item.ron".to_string(),
+                },
+                AssetError::UnresolvedLoot {
+                    table: "loot_tables/broken.loot.ron".to_string(),
+                    path: "items/coin.item.ron".to_string(),
+                },
+                AssetError::UnresolvedLoot {
+                    table: "loot_tables/broken.loot.ron".to_string(),
+                    path: "loot_tables/rare.loot.ron".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -1,7 +1,11 @@
 use bevy::{
     app::Plugin,
-    asset::{Asset, AssetApp, Assets, AsyncReadExt},
-    ecs::system::Res,
+    asset::{Asset, AssetApp, AsyncReadExt},
+    ecs::{
+        entity::{Entity, MapEntities},
+        event::Event,
+        schedule::{IntoSystemConfigs, OnExit},
+    },
     reflect::{std_traits::ReflectDefault, Reflect},
     utils::hashbrown::HashMap,
 };
@@ -10,9 +14,13 @@ use serde::{Deserialize, Serialize};
 use crate::{
     asset_macro::impl_asset_loader,
     asset_ref::{AssetRef, Loadable},
+    GameState,
 };
 
-use super::{Item, ItemBundle, ItemStack, Layout};
+use super::{
+    build_recipe_index, check_recipe_graph, CraftingGrid, Item, ItemBundle, ItemDefinitions,
+    ItemStack, ItemsLayout, Layout, RecipeIndex,
+};
 
 pub struct WorkbenchPlugin;
 
@@ -20,7 +28,12 @@ impl Plugin for WorkbenchPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.init_asset::<Workbench>()
             .register_asset_loader(WorkbenchAssetLoader)
-            .register_asset_reflect::<Workbench>();
+            .register_asset_reflect::<Workbench>()
+            .init_resource::<RecipeIndex>()
+            .add_systems(
+                OnExit(GameState::Loading),
+                (build_recipe_index, check_recipe_graph).chain(),
+            );
     }
 }
 
@@ -28,45 +41,214 @@ impl Plugin for WorkbenchPlugin {
 #[reflect(Default)]
 pub struct Workbench {
     name: String,
-    recipes: HashMap<Vec<(AssetRef<Item>, ItemStack)>, Vec<(AssetRef<Item>, ItemStack)>>,
+    /// Shapeless recipes, input to output.
+    recipes: HashMap<RecipeInput, RecipeOutput>,
+    #[serde(default)]
+    shaped: Vec<ShapedRecipe>,
+}
+
+/// Output of a [`Workbench`] recipe as declared in the asset.
+pub type RecipeItems = Vec<(AssetRef<Item>, ItemStack)>;
+
+/// Input of a [`Workbench`] recipe as declared in the asset.
+pub type RecipeInput = Vec<(IngredientRef, ItemStack)>;
+
+/// Output of a shapeless [`Workbench`] recipe as declared in the asset:
+/// `(items: [(Path("items/item2.item.ron"), ItemStack(1))], time: 2.0)`.
+#[derive(Debug, Clone, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub struct RecipeOutput {
+    pub items: RecipeItems,
+    /// Crafting time in seconds.
+    #[serde(default)]
+    pub time: f32,
+}
+
+impl Loadable for RecipeOutput {
+    fn load(&mut self, load_context: &mut bevy::asset::LoadContext) {
+        self.items.load(load_context);
+    }
+}
+
+/// Ingredient of a [`Workbench`] recipe as declared in the asset:
+/// `Path("items/item1.item.ron")` for a specific item or `Tag("wood")`
+/// for any item with the tag.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+#[serde(from = "IngredientDef", into = "IngredientDef")]
+pub enum IngredientRef {
+    Item(AssetRef<Item>),
+    Tag(String),
+}
+
+impl Default for IngredientRef {
+    fn default() -> Self {
+        Self::Item(AssetRef::default())
+    }
+}
+
+impl Loadable for IngredientRef {
+    fn load(&mut self, load_context: &mut bevy::asset::LoadContext) {
+        if let Self::Item(item) = self {
+            item.load(load_context);
+        }
+    }
+}
+
+/// Keeps item paths in the same format as [`AssetRef`].
+#[derive(Serialize, Deserialize)]
+enum IngredientDef {
+    Path(String),
+    Tag(String),
+}
+
+impl From<IngredientDef> for IngredientRef {
+    fn from(value: IngredientDef) -> Self {
+        match value {
+            IngredientDef::Path(path) => Self::Item(AssetRef::Path(path)),
+            IngredientDef::Tag(tag) => Self::Tag(tag),
+        }
+    }
+}
+
+impl From<IngredientRef> for IngredientDef {
+    fn from(value: IngredientRef) -> Self {
+        match value {
+            IngredientRef::Item(item) => Self::Path(item.get_path().unwrap_or_default()),
+            IngredientRef::Tag(tag) => Self::Tag(tag),
+        }
+    }
+}
+
+/// Recipe that requires items to be placed in a [`CraftingGrid`](super::CraftingGrid)
+/// in a specific pattern. The mirrored pattern is accepted too.
+///
+/// ```ron
+/// (
+///     pattern: ["ww", " w"],
+///     key: { 'w': Tag("wood") },
+///     output: [(Path("items/item2.item.ron"), ItemStack(1))],
+///     time: 2.0,
+/// )
+/// ```
+#[derive(Debug, Clone, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub struct ShapedRecipe {
+    /// Rows of the pattern. Every character is a key of an ingredient, `' '` is an empty cell.
+    pub pattern: Vec<String>,
+    pub key: HashMap<char, IngredientRef>,
+    pub output: RecipeItems,
+    /// Crafting time in seconds.
+    #[serde(default)]
+    pub time: f32,
+}
+
+impl Loadable for ShapedRecipe {
+    fn load(&mut self, load_context: &mut bevy::asset::LoadContext) {
+        self.key.load(load_context);
+        self.output.load(load_context);
+    }
+}
+
+/// Request to craft a recipe at the [`WorkbenchStation`](super::WorkbenchStation)
+/// from the player's inventory. It's processed by the server: the ingredients are taken
+/// immediately and a job is added to the station's [`CraftingQueue`](super::CraftingQueue).
+#[derive(Debug, Clone, Event, Serialize, Deserialize)]
+pub struct CraftRequest {
+    pub station: Entity,
+    pub recipe: CraftInput,
+}
+
+impl MapEntities for CraftRequest {
+    fn map_entities<M: bevy::prelude::EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.station = entity_mapper.map_entity(self.station);
+    }
+}
+
+/// Items offered for a recipe.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CraftInput {
+    /// Matched against shapeless recipes.
+    Shapeless(ItemsLayout),
+    /// Matched against shaped recipes.
+    Shaped(CraftingGrid),
+}
+
+impl CraftInput {
+    /// Replaces the items sent by the client with the server's definitions of their ids,
+    /// only the ids and the amounts are kept. Returns `None` if an id is unknown.
+    pub fn resolve(&self, definitions: &ItemDefinitions) -> Option<Self> {
+        let resolve = |bundle: &ItemBundle| {
+            Some(ItemBundle {
+                item: definitions.get(&bundle.item.id)?.clone(),
+                stack: bundle.stack.clone(),
+            })
+        };
+        match self {
+            CraftInput::Shapeless(layout) => layout
+                .0
+                .iter()
+                .map(resolve)
+                .collect::<Option<_>>()
+                .map(|items| CraftInput::Shapeless(Layout(items))),
+            CraftInput::Shaped(grid) => {
+                let mut resolved = CraftingGrid::new(grid.width(), grid.height());
+                for y in 0..grid.height() {
+                    for x in 0..grid.width() {
+                        if let Some(bundle) = grid.get(x, y) {
+                            resolved.set(x, y, Some(resolve(bundle)?));
+                        }
+                    }
+                }
+                Some(CraftInput::Shaped(resolved))
+            }
+        }
+    }
+}
+
+/// Sent by the server to the client whose [`CraftRequest`] failed
+/// or whose crafting job was finished or cancelled.
+#[derive(Debug, Clone, Event, Serialize, Deserialize)]
+pub struct CraftResult {
+    pub workbench: String,
+    /// Crafted items on success.
+    pub result: Result<ItemsLayout, CraftError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CraftError {
+    NoPlayer,
+    UnknownWorkbench,
+    UnknownRecipe,
+    /// An offered item has an unknown id.
+    UnknownItem,
+    /// The player hasn't unlocked the recipe yet.
+    Locked,
+    /// The player is too far from the station.
+    TooFar,
+    MissingIngredients,
+    /// Crafted or refunded items would not fit into the inventory.
+    NotEnoughSpace,
+    /// The job was cancelled, the ingredients are refunded.
+    Cancelled,
 }
 
 impl_asset_loader! {
     Workbench &["workbench.ron"];
-    recipes
+    recipes shaped
 }
 
 impl Loadable for ItemStack {}
 
 impl Workbench {
-    pub fn craft<'a>(
-        &'a self,
-        assets: &'a Res<Assets<Item>>,
-        layout: &'a Layout<ItemBundle>,
-    ) -> Option<Vec<(&Item, &ItemStack)>> {
-        // It's expensive, I know
-        // FIXME
-        let map = self
-            .recipes
-            .iter()
-            .map(|(k, v)| {
-                (
-                    k.iter()
-                        .filter_map(|(h, s)| assets.get(h.get_handle().unwrap()).map(|a| (a, s)))
-                        .collect(),
-                    v.iter()
-                        .filter_map(|(h, s)| assets.get(h.get_handle().unwrap()).map(|a| (a, s)))
-                        .collect(),
-                )
-            })
-            .collect::<HashMap<Vec<(&Item, &ItemStack)>, Vec<(&Item, &ItemStack)>>>();
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-        let layout = layout
-            .get()
-            .iter()
-            .map(|b| (&b.item, &b.stack))
-            .collect::<Vec<_>>();
+    pub fn recipes(&self) -> &HashMap<RecipeInput, RecipeOutput> {
+        &self.recipes
+    }
 
-        map.get(&layout).cloned()
+    pub fn shaped(&self) -> &[ShapedRecipe] {
+        &self.shaped
     }
 }
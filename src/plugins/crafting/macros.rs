@@ -10,16 +10,19 @@ use super::logic::Item;
 /// Example:
 /// ```rust
 /// item_kind!(primitive) // ItemKind::Primitive
-/// item_kind!(complex {}) // ItemKind::Complex(ItemProperties {})
+/// item_kind!(complex { speed: 0.1 }) // ItemKind::Complex(ItemProperties { speed: 0.1, .. })
 /// ```
 #[macro_export]
 macro_rules! item_kind {
     (primitive) => {
         $crate::plugins::crafting::logic::ItemKind::Primitive
     };
-    (complex {}) => {
+    (complex { $($stat:ident: $value:expr),* $(,)? }) => {
         $crate::plugins::crafting::logic::ItemKind::Complex(
-            $crate::plugins::crafting::logic::ItemProperties {},
+            $crate::plugins::crafting::logic::ItemProperties {
+                $($stat: $value,)*
+                ..Default::default()
+            },
         )
     };
 }
@@ -37,9 +40,13 @@ macro_rules! item {
         level = $level:literal
     ) => {
         Item {
+            id: $crate::plugins::crafting::logic::ItemId($name.to_string()),
             name: $name.to_string(),
             kind: $kind,
             level: $level,
+            max_stack: $crate::plugins::crafting::logic::Item::DEFAULT_MAX_STACK,
+            tags: Vec::new(),
+            equip: None,
         }
     };
     (
@@ -50,9 +57,13 @@ macro_rules! item {
     ) => {
         $crate::plugins::crafting::logic::ItemBundle {
             item: $crate::plugins::crafting::logic::Item {
+                id: $crate::plugins::crafting::logic::ItemId($name.to_string()),
                 name: $name.to_string(),
                 kind: $kind,
                 level: $level,
+                max_stack: $crate::plugins::crafting::logic::Item::DEFAULT_MAX_STACK,
+                tags: Vec::new(),
+                equip: None,
             },
             stack: $crate::plugins::crafting::logic::ItemStack($amount),
         }
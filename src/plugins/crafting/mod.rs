@@ -1,32 +1,39 @@
 use bevy::{
-    app::Plugin,
-    asset::AssetApp,
-    ecs::{component::Component, entity::Entity, system::Commands},
-    reflect::{std_traits::ReflectDefault, Reflect},
+    app::Plugin, asset::AssetApp, ecs::schedule::OnExit, reflect::std_traits::ReflectDefault,
 };
-use bevy_common_assets::ron::RonAssetPlugin;
 use bevy_replicon::{
     core::{replication_rules::AppReplicationExt, replicon_channels::ChannelKind},
-    network_event::client_event::ClientEventAppExt,
+    network_event::{client_event::ClientEventAppExt, server_event::ServerEventAppExt},
 };
 
-use std::sync::Arc;
-
 use self::{
+    dropped::DroppedItemsPlugin,
+    enchanting::EnchantingTablePlugin,
+    equipment::EquipmentPlugin,
     logic::{
-        Inventory, Item, ItemEvent, ItemKind, ItemProperties, ItemStack,
-        WorkbenchPlugin,
+        build_item_registry, CancelCraft, CraftRequest, CraftResult, CraftingQueue, CustomName,
+        DropItem, DroppedItem, Durability, EnchantItem, EnchantingTable, Enchantment,
+        EnchantmentPlugin, EquipItem, Equipment, EquipmentKind, HotReloadPlugin, Inventory, Item,
+        ItemAssetLoader, ItemEnchantments, ItemEvent, ItemEventResponse, ItemId, ItemKind,
+        ItemMetadata, ItemProperties, ItemRegistry, ItemStack, LootPlugin, SortInventory,
+        TransferItem, UnequipItem, UnlockedRecipes, WorkbenchPlugin, WorkbenchStation,
     },
+    station::WorkbenchStationPlugin,
     systems::WindowSystemsPlugin,
 };
 
-use super::player::PlayerProperties;
+use crate::GameState;
 
+mod dropped;
+mod enchanting;
+mod equipment;
 pub mod logic;
 mod macros;
+mod station;
 mod systems;
 
-pub use systems::{show_item, ItemsCollection, WorkbenchesCollection};
+pub use enchanting::EnchantmentsCollection;
+pub use systems::{show_item, show_search, ItemsCollection, WorkbenchesCollection};
 
 pub struct CraftingPlugin;
 
@@ -40,88 +47,59 @@ impl Plugin for CraftingPlugin {
             .register_type_data::<Vec<Item>, ReflectDefault>()
             .register_type::<Item>()
             .register_type::<ItemKind>()
+            .register_type::<ItemId>()
             .register_type::<ItemStack>()
             .register_type::<ItemProperties>()
-            .replicate::<Item>()
+            .register_type::<Durability>()
+            .register_type::<CustomName>()
+            .register_type::<ItemMetadata>()
+            .register_type::<Enchantment>()
+            .register_type::<ItemEnchantments>()
+            .register_type::<EnchantingTable>()
+            .register_type::<DroppedItem>()
+            .register_type::<Equipment>()
+            .register_type::<EquipmentKind>()
+            .register_type::<Option<EquipmentKind>>()
+            .register_type::<WorkbenchStation>()
+            .replicate::<ItemId>()
             .replicate::<ItemStack>()
+            .replicate::<Durability>()
+            .replicate::<CustomName>()
+            .replicate::<ItemMetadata>()
+            .replicate::<ItemEnchantments>()
+            .replicate::<EnchantingTable>()
+            .replicate::<DroppedItem>()
+            .replicate_mapped::<Equipment>()
             .replicate_mapped::<Inventory>()
+            .replicate::<WorkbenchStation>()
+            .replicate_mapped::<CraftingQueue>()
+            .replicate::<UnlockedRecipes>()
             .add_mapped_client_event::<ItemEvent>(ChannelKind::Ordered)
-            .add_plugins(RonAssetPlugin::<Item>::new(&["item.ron"]))
+            .add_mapped_client_event::<TransferItem>(ChannelKind::Ordered)
+            .add_client_event::<SortInventory>(ChannelKind::Ordered)
+            .add_mapped_server_event::<ItemEventResponse>(ChannelKind::Ordered)
+            .add_mapped_client_event::<CraftRequest>(ChannelKind::Ordered)
+            .add_mapped_client_event::<CancelCraft>(ChannelKind::Ordered)
+            .add_server_event::<CraftResult>(ChannelKind::Ordered)
+            .add_mapped_client_event::<EnchantItem>(ChannelKind::Ordered)
+            .add_client_event::<EquipItem>(ChannelKind::Ordered)
+            .add_client_event::<UnequipItem>(ChannelKind::Ordered)
+            .add_client_event::<DropItem>(ChannelKind::Ordered)
+            .init_asset::<Item>()
+            .register_asset_loader(ItemAssetLoader)
+            .init_resource::<ItemRegistry>()
+            .add_systems(OnExit(GameState::Loading), build_item_registry)
             .register_asset_reflect::<Item>()
-            .add_plugins((WindowSystemsPlugin, WorkbenchPlugin));
-    }
-}
-
-#[derive(Component)]
-pub struct ItemEnchantments(Vec<EnchantmentWithState>);
-
-struct EnchantmentWithState {
-    enchantment: Arc<dyn Enchantment>,
-    state: bool,
-}
-
-impl ItemEnchantments {
-    pub fn add(&mut self, enchantment: Arc<dyn Enchantment>) {
-        self.0.push(EnchantmentWithState {
-            enchantment,
-            state: false,
-        })
-    }
-
-    pub fn apply_unapplied(
-        &mut self,
-        player_properties: &mut PlayerProperties,
-        item_properties: &mut ItemProperties,
-        commands: &mut Commands,
-        player_entity: Entity,
-    ) {
-        self.0.iter_mut().filter(|i| !i.state).for_each(|item| {
-            item.enchantment.modify_item_properties(item_properties);
-            item.enchantment.modify_player_properties(player_properties);
-            item.enchantment.modify_world(commands, player_entity);
-
-            item.state = true
-        });
-    }
-}
-
-// Just make sure that we don't add something that not allows us to construct a trait-object
-const _: Option<Box<dyn Enchantment>> = None;
-
-/// It represents additional modifiers for [`ItemProperties`] and [`ItemProperties`].
-/// Also you can access [`Commands`] and player's [`Entity`]
-/// using [`Enchantment::modify_world`] method
-///
-/// Methods in this trait are only called once when this `Enchantment` is applied
-pub trait Enchantment: Reflect {
-    fn modify_player_properties(&self, _properties: &mut PlayerProperties) {}
-    fn modify_item_properties(&self, _properties: &mut ItemProperties) {}
-    fn modify_world(&self, _commands: &mut Commands, _player_entity: Entity) {}
-}
-
-#[derive(Component)]
-pub struct EnchantingTable;
-
-impl EnchantingTable {
-    pub fn enchant(
-        &self,
-        item_enchantments: &mut ItemEnchantments,
-        enchantment: Arc<dyn Enchantment>,
-    ) {
-        item_enchantments.add(enchantment)
-    }
-}
-
-#[derive(Reflect)]
-struct Power;
-
-#[derive(Component)]
-struct PowerTag;
-
-impl Enchantment for Power {
-    fn modify_world(&self, commands: &mut Commands, player_entity: Entity) {
-        if let Some(mut player) = commands.get_entity(player_entity) {
-            player.insert(PowerTag);
-        }
+            .add_plugins((
+                WindowSystemsPlugin,
+                WorkbenchPlugin,
+                WorkbenchStationPlugin,
+                HotReloadPlugin,
+                EnchantmentPlugin,
+                LootPlugin,
+                EnchantingTablePlugin,
+                EquipmentPlugin,
+                DroppedItemsPlugin,
+            ));
     }
 }
@@ -0,0 +1,589 @@
+use bevy::{
+    app::{Plugin, PreUpdate, Update},
+    asset::{AssetId, Assets, Handle},
+    core::Name,
+    ecs::{
+        change_detection::Mut,
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        query::{Added, Changed, With, Without},
+        schedule::{
+            common_conditions::{in_state, resource_exists},
+            IntoSystemConfigs, OnEnter,
+        },
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    log::{info, warn},
+    math::Vec2,
+    render::{color::Color, texture::Image, view::VisibilityBundle},
+    sprite::Sprite,
+    time::Time,
+    transform::components::{GlobalTransform, Transform},
+};
+use bevy_inspector_egui::{
+    bevy_egui::{egui, EguiContexts},
+    egui::Ui,
+};
+use bevy_mod_picking::{
+    events::{Click, Pointer},
+    prelude::{ListenerInput, On},
+    PickableBundle,
+};
+use bevy_replicon::{
+    client::ClientSet,
+    core::{common_conditions::has_authority, replication_rules::Replication},
+    network_event::{
+        client_event::FromClient,
+        server_event::{SendMode, ToClients},
+    },
+};
+use bevy_replicon_renet::renet::RenetServer;
+
+use crate::{
+    plugins::player::{LocalPLayer, Player, INTERACTION_DISTANCE_SQUARED},
+    utils::squared_distance,
+    GameState,
+};
+
+use super::{
+    logic::{
+        CancelCraft, CraftError, CraftInput, CraftRequest, CraftResult, CraftingQueue, Ingredient,
+        InstanceData, Inventory, ItemBundle, ItemDefinitions, ItemId, ItemStack, Layout,
+        RecipeIndex, TransferItem, UnlockedRecipes, Workbench, WorkbenchStation,
+    },
+    show_item, WorkbenchesCollection,
+};
+
+pub struct WorkbenchStationPlugin;
+
+impl Plugin for WorkbenchStationPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_event::<CheckWorkbench>()
+            .add_systems(
+                OnEnter(GameState::Game),
+                spawn_workbench_stations.run_if(resource_exists::<RenetServer>),
+            )
+            .add_systems(PreUpdate, station_init_system.after(ClientSet::Receive))
+            .add_systems(
+                Update,
+                (
+                    (
+                        unlock_recipes,
+                        craft_request,
+                        cancel_craft,
+                        progress_crafting_jobs,
+                    )
+                        .chain()
+                        .run_if(has_authority),
+                    craft_result,
+                    check_workbench,
+                    handle_workbench_window.after(check_workbench),
+                )
+                    .run_if(in_state(GameState::Game)),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct WorkbenchWindowState {
+    pub is_open: bool,
+    pub station: Entity,
+}
+
+#[derive(Event, Debug)]
+struct CheckWorkbench {
+    pub station: Entity,
+}
+
+impl From<ListenerInput<Pointer<Click>>> for CheckWorkbench {
+    fn from(value: ListenerInput<Pointer<Click>>) -> Self {
+        Self {
+            station: value.target,
+        }
+    }
+}
+
+/// Places a station for every loaded workbench in a row, with an empty queue and output.
+fn spawn_workbench_stations(mut commands: Commands, workbenches: Res<WorkbenchesCollection>) {
+    for (i, (path, _)) in workbenches.iter().enumerate() {
+        commands.spawn((
+            WorkbenchStation {
+                workbench: path.to_string(),
+            },
+            CraftingQueue::default(),
+            Inventory::with_capacity(WorkbenchStation::OUTPUT_CAPACITY),
+            Transform::from_xyz(-20.0 - 30.0 * i as f32, 10.0, 10.0),
+            Replication,
+        ));
+    }
+}
+
+/// Binds spawned stations to the [`Workbench`] assets at their paths.
+fn station_init_system(
+    mut commands: Commands,
+    spawned_stations: Query<(Entity, &WorkbenchStation), Added<WorkbenchStation>>,
+    workbenches: Res<WorkbenchesCollection>,
+) {
+    for (entity, station) in &spawned_stations {
+        let mut entity = commands.entity(entity);
+        entity.insert((
+            Name::new(format!("Workbench {}", station.workbench)),
+            Sprite {
+                custom_size: Some(Vec2::new(10.0, 10.0)),
+                color: Color::ORANGE,
+                ..Default::default()
+            },
+            Handle::<Image>::default(),
+            GlobalTransform::default(),
+            VisibilityBundle::default(),
+            PickableBundle::default(),
+            On::<Pointer<Click>>::send_event::<CheckWorkbench>(),
+        ));
+
+        match workbenches.get(&station.workbench) {
+            Some(handle) => {
+                entity.insert(handle.clone());
+            }
+            None => warn!("Station of unknown workbench {:?}", station.workbench),
+        }
+    }
+}
+
+fn check_workbench(
+    mut commands: Commands,
+    mut check_event: EventReader<CheckWorkbench>,
+    player_query: Query<&GlobalTransform, With<LocalPLayer>>,
+    station_query: Query<&GlobalTransform, (With<WorkbenchStation>, Without<LocalPLayer>)>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    for CheckWorkbench { station } in check_event.read() {
+        let Ok(station_transform) = station_query.get(*station) else {
+            continue;
+        };
+        if squared_distance(
+            player_transform.translation(),
+            station_transform.translation(),
+        ) <= INTERACTION_DISTANCE_SQUARED
+        {
+            commands.insert_resource(WorkbenchWindowState {
+                is_open: true,
+                station: *station,
+            });
+        }
+    }
+}
+
+fn craft_request(
+    mut requests: EventReader<FromClient<CraftRequest>>,
+    mut results: EventWriter<ToClients<CraftResult>>,
+    mut player_query: Query<(
+        Entity,
+        &mut Inventory,
+        &UnlockedRecipes,
+        &GlobalTransform,
+        &Player,
+    )>,
+    mut stations: Query<
+        (&Handle<Workbench>, &mut CraftingQueue, &GlobalTransform),
+        Without<Player>,
+    >,
+    workbench_assets: Res<Assets<Workbench>>,
+    recipes: Res<RecipeIndex>,
+    definitions: ItemDefinitions,
+    mut items_query: Query<(&ItemId, &mut ItemStack, InstanceData)>,
+) {
+    for FromClient { client_id, event } in requests.read() {
+        let result = match (
+            player_query
+                .iter_mut()
+                .find(|(.., player)| player.0 == *client_id),
+            stations.get_mut(event.station),
+        ) {
+            (None, _) => Err(CraftError::NoPlayer),
+            (_, Err(_)) => Err(CraftError::UnknownWorkbench),
+            (
+                Some((player_entity, mut inventory, unlocked, player_transform, _)),
+                Ok((workbench, mut queue, station_transform)),
+            ) => {
+                if squared_distance(
+                    player_transform.translation(),
+                    station_transform.translation(),
+                ) > INTERACTION_DISTANCE_SQUARED
+                {
+                    Err(CraftError::TooFar)
+                } else {
+                    enqueue_craft(
+                        player_entity,
+                        &mut inventory,
+                        unlocked,
+                        &mut items_query,
+                        &mut queue,
+                        &recipes,
+                        workbench.id(),
+                        &event.recipe,
+                        &definitions,
+                    )
+                }
+            }
+        };
+
+        if let Err(err) = result {
+            warn!(
+                "{client_id:?} failed to craft at {:?}: {err:?}",
+                event.station
+            );
+            results.send(ToClients {
+                mode: SendMode::Direct(*client_id),
+                event: CraftResult {
+                    workbench: stations
+                        .get(event.station)
+                        .map(|(workbench, ..)| workbench_name(&workbench_assets, workbench))
+                        .unwrap_or_default(),
+                    result: Err(err),
+                },
+            });
+        }
+    }
+}
+
+/// Unlocks the recipes that use the items the players have obtained.
+fn unlock_recipes(
+    mut players: Query<(&Inventory, &mut UnlockedRecipes), Changed<Inventory>>,
+    items_query: Query<(&ItemId, &ItemStack)>,
+    definitions: ItemDefinitions,
+    recipes: Res<RecipeIndex>,
+) {
+    for (inventory, mut unlocked) in players.iter_mut() {
+        for (item, _) in inventory.items(&items_query, &definitions) {
+            for recipe in recipes.consuming(item) {
+                if !unlocked.contains(recipe) {
+                    unlocked.unlock(recipe);
+                }
+            }
+        }
+    }
+}
+
+/// Takes the ingredients of the recipe matching the `input` from the `inventory`
+/// and adds a crafting job to the `queue`. The `input` is matched with the server's
+/// definitions of the offered items.
+///
+/// Nothing is changed if there is no such recipe or not enough ingredients.
+fn enqueue_craft(
+    owner: Entity,
+    inventory: &mut Inventory,
+    unlocked: &UnlockedRecipes,
+    items_query: &mut Query<(&ItemId, &mut ItemStack, InstanceData)>,
+    queue: &mut CraftingQueue,
+    recipes: &RecipeIndex,
+    workbench: AssetId<Workbench>,
+    input: &CraftInput,
+    definitions: &ItemDefinitions,
+) -> Result<(), CraftError> {
+    let input = input.resolve(definitions).ok_or(CraftError::UnknownItem)?;
+    let found = match &input {
+        CraftInput::Shapeless(layout) => recipes.find(workbench, layout),
+        CraftInput::Shaped(grid) => recipes.find_shaped(workbench, grid),
+    }
+    .ok_or(CraftError::UnknownRecipe)?;
+    if !unlocked.contains(found.recipe) {
+        return Err(CraftError::Locked);
+    }
+    let input = found
+        .consumed
+        .iter()
+        .map(ItemBundle::as_tuple)
+        .collect::<Vec<_>>();
+
+    inventory.check_exchange(&items_query.to_readonly(), &input, &[])?;
+
+    for (item, stack) in input {
+        inventory.remove_item(items_query, &item.id, stack.0);
+    }
+
+    queue.push(
+        owner,
+        found.consumed,
+        found.recipe.output.clone(),
+        found.recipe.duration,
+    );
+    Ok(())
+}
+
+/// Advances the first job of every [`CraftingQueue`] and delivers finished jobs.
+fn progress_crafting_jobs(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut results: EventWriter<ToClients<CraftResult>>,
+    mut stations: Query<(&Handle<Workbench>, &mut CraftingQueue, &mut Inventory), Without<Player>>,
+    mut players: Query<(&mut Inventory, &Player)>,
+    mut items_query: Query<(&ItemId, &mut ItemStack, InstanceData)>,
+    workbench_assets: Res<Assets<Workbench>>,
+) {
+    for (workbench, mut queue, mut station_inventory) in stations.iter_mut() {
+        let Some(job) = queue.jobs.front_mut() else {
+            continue;
+        };
+        job.elapsed += time.delta_seconds();
+        if !job.is_finished() {
+            continue;
+        }
+
+        let player = players.get_mut(job.owner).ok();
+        let client_id = player.as_ref().map(|(_, player)| player.0);
+        // A finished job stays first in the queue until its output fits somewhere
+        if !deliver_items(
+            &mut commands,
+            &mut items_query,
+            player.map(|(inventory, _)| inventory),
+            &mut station_inventory,
+            &job.output,
+        ) {
+            continue;
+        }
+        let Some(job) = queue.jobs.pop_front() else {
+            continue;
+        };
+
+        if let Some(client_id) = client_id {
+            results.send(ToClients {
+                mode: SendMode::Direct(client_id),
+                event: CraftResult {
+                    workbench: workbench_name(&workbench_assets, workbench),
+                    result: Ok(Layout(job.output)),
+                },
+            });
+        }
+    }
+}
+
+fn cancel_craft(
+    mut commands: Commands,
+    mut cancel_events: EventReader<FromClient<CancelCraft>>,
+    mut results: EventWriter<ToClients<CraftResult>>,
+    mut stations: Query<(&Handle<Workbench>, &mut CraftingQueue, &mut Inventory), Without<Player>>,
+    mut players: Query<(Entity, &mut Inventory, &Player)>,
+    mut items_query: Query<(&ItemId, &mut ItemStack, InstanceData)>,
+    workbench_assets: Res<Assets<Workbench>>,
+) {
+    for FromClient { client_id, event } in cancel_events.read() {
+        let Some((player_entity, player_inventory, _)) = players
+            .iter_mut()
+            .find(|(_, _, player)| player.0 == *client_id)
+        else {
+            continue;
+        };
+        let Ok((workbench, mut queue, mut station_inventory)) = stations.get_mut(event.station)
+        else {
+            continue;
+        };
+        let Some(job) = queue
+            .jobs
+            .iter()
+            .find(|job| job.id == event.job && job.owner == player_entity)
+        else {
+            warn!("{client_id:?} tried to cancel an unknown job {}", event.job);
+            continue;
+        };
+
+        // The job is kept if there is no room for the refund
+        let result = if deliver_items(
+            &mut commands,
+            &mut items_query,
+            Some(player_inventory),
+            &mut station_inventory,
+            &job.consumed,
+        ) {
+            queue.cancel(player_entity, event.job);
+            Err(CraftError::Cancelled)
+        } else {
+            Err(CraftError::NotEnoughSpace)
+        };
+
+        results.send(ToClients {
+            mode: SendMode::Direct(*client_id),
+            event: CraftResult {
+                workbench: workbench_name(&workbench_assets, workbench),
+                result,
+            },
+        });
+    }
+}
+
+/// Adds the `items` to the player's inventory or, if they don't fit there, to the station's
+/// inventory, which works as an output buffer. Nothing is added if they fit into neither.
+///
+/// Returns whether the items were added.
+fn deliver_items(
+    commands: &mut Commands,
+    items_query: &mut Query<(&ItemId, &mut ItemStack, InstanceData)>,
+    player_inventory: Option<Mut<Inventory>>,
+    station_inventory: &mut Inventory,
+    items: &[ItemBundle],
+) -> bool {
+    let items = items.iter().map(ItemBundle::as_tuple).collect::<Vec<_>>();
+    let fits = |inventory: &Inventory| {
+        inventory
+            .check_exchange(&items_query.to_readonly(), &[], &items)
+            .is_ok()
+    };
+    let inventory = match player_inventory {
+        Some(inventory) if fits(&inventory) => inventory.into_inner(),
+        _ if fits(station_inventory) => station_inventory,
+        _ => return false,
+    };
+    inventory.add_combine(commands, items_query, items);
+    true
+}
+
+/// Name of the station's workbench for the [`CraftResult`]s.
+fn workbench_name(workbench_assets: &Assets<Workbench>, workbench: &Handle<Workbench>) -> String {
+    workbench_assets
+        .get(workbench)
+        .map(|workbench| workbench.name().to_string())
+        .unwrap_or_default()
+}
+
+fn craft_result(mut results: EventReader<CraftResult>) {
+    for CraftResult { workbench, result } in results.read() {
+        match result {
+            Ok(crafted) => info!("Crafted in {workbench:?}: {crafted:?}"),
+            Err(err) => warn!("Crafting in {workbench:?} failed: {err:?}"),
+        }
+    }
+}
+
+fn show_ingredient(ingredient: &Ingredient, stack: &ItemStack, ui: &mut Ui, enabled: bool) {
+    match ingredient {
+        Ingredient::Item(item) => show_item((item, stack), ui, enabled),
+        Ingredient::Tag(tag) => {
+            ui.add_enabled(
+                enabled,
+                egui::Label::new(format!("any #{tag} x{}", stack.0)),
+            );
+        }
+    }
+}
+
+/// Recipes of the opened station, its crafting queue and the crafted items
+/// that didn't fit into the player's inventory.
+fn handle_workbench_window(
+    mut contexts: EguiContexts,
+    window_state: Option<ResMut<WorkbenchWindowState>>,
+    stations: Query<(&Handle<Workbench>, &CraftingQueue, &Inventory)>,
+    workbench_assets: Res<Assets<Workbench>>,
+    player_query: Query<(Entity, &Inventory, &UnlockedRecipes), With<LocalPLayer>>,
+    items_query: Query<(&ItemId, &ItemStack)>,
+    definitions: ItemDefinitions,
+    recipes: Res<RecipeIndex>,
+    mut craft_requests: EventWriter<CraftRequest>,
+    mut cancel_requests: EventWriter<CancelCraft>,
+    mut transfer_events: EventWriter<TransferItem>,
+) {
+    let Some(mut window_state) = window_state else {
+        return;
+    };
+    let station_entity = window_state.station;
+    let (
+        Ok((workbench, queue, station_inventory)),
+        Ok((player_entity, player_inventory, unlocked)),
+    ) = (stations.get(station_entity), player_query.get_single())
+    else {
+        return;
+    };
+    let available = player_inventory.items(&items_query, &definitions);
+
+    egui::Window::new(workbench_name(&workbench_assets, workbench))
+        .open(&mut window_state.is_open)
+        .resizable(true)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.heading("Recipes");
+            for recipe in recipes
+                .workbench_recipes(workbench.id())
+                .filter(|recipe| unlocked.contains(recipe))
+            {
+                let input = recipe.offer(&available);
+                let enabled = input.is_some();
+
+                ui.horizontal(|ui| {
+                    for (ingredient, stack) in &recipe.input {
+                        show_ingredient(ingredient, stack, ui, enabled);
+                    }
+                    if recipe.shape.is_some() {
+                        ui.label("(shaped)");
+                    }
+
+                    ui.separator();
+
+                    for bundle in &recipe.output {
+                        show_item(bundle.as_tuple(), ui, enabled);
+                    }
+                    if recipe.duration > 0.0 {
+                        ui.label(format!("{:.1}s", recipe.duration));
+                    }
+
+                    if ui
+                        .add_enabled(enabled, egui::Button::new("Craft"))
+                        .clicked()
+                    {
+                        if let Some(input) = input {
+                            craft_requests.send(CraftRequest {
+                                station: station_entity,
+                                recipe: input,
+                            });
+                        }
+                    }
+                });
+            }
+
+            ui.separator();
+            ui.heading("Queue");
+            for job in &queue.jobs {
+                ui.horizontal(|ui| {
+                    let output = job
+                        .output
+                        .iter()
+                        .map(|bundle| format!("{} x{}", bundle.item.name, bundle.stack.0))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    ui.add(egui::ProgressBar::new(job.progress()).text(output));
+                    if job.owner == player_entity && ui.button("Cancel").clicked() {
+                        cancel_requests.send(CancelCraft {
+                            station: station_entity,
+                            job: job.id,
+                        });
+                    }
+                });
+            }
+
+            ui.separator();
+            ui.heading("Output");
+            for (slot, entity) in station_inventory
+                .map
+                .iter()
+                .enumerate()
+                .filter_map(|(slot, opt)| opt.map(|entity| (slot, entity)))
+            {
+                let Some((item, stack)) = items_query
+                    .get(entity)
+                    .ok()
+                    .and_then(|(id, stack)| Some((definitions.get(id)?, stack)))
+                else {
+                    continue;
+                };
+                ui.horizontal(|ui| {
+                    show_item((item, stack), ui, true);
+                    if ui.button("Take").clicked() {
+                        transfer_events.send(TransferItem {
+                            from: station_entity,
+                            to: player_entity,
+                            slot,
+                            amount: stack.0,
+                        });
+                    }
+                });
+            }
+        });
+}
@@ -1,15 +1,18 @@
 use bevy::{
-    app::{Plugin, Update},
+    app::{Plugin, PostUpdate, Update},
     asset::{Assets, Handle},
     ecs::{
-        event::{Event, EventReader, EventWriter},
-        query::With,
+        entity::Entity,
+        event::{EventReader, EventWriter},
+        query::{Changed, Has},
         reflect::AppTypeRegistry,
         schedule::{common_conditions::in_state, IntoSystemConfigs},
-        system::{Commands, Query, Res, ResMut, Resource},
+        system::{Commands, Local, Query, Res, ResMut, Resource},
     },
-    log::{error, warn},
+    hierarchy::DespawnRecursiveExt,
+    log::warn,
     reflect::TypePath,
+    transform::components::GlobalTransform,
 };
 use bevy_asset_loader::asset_collection::AssetCollection;
 use bevy_inspector_egui::{
@@ -17,17 +20,25 @@ use bevy_inspector_egui::{
     egui::{self, Ui},
 };
 use bevy_replicon::{
-    core::common_conditions::has_authority, network_event::client_event::FromClient,
+    core::common_conditions::has_authority,
+    network_event::{
+        client_event::FromClient,
+        server_event::{SendMode, ToClients},
+    },
+    server::ServerSet,
 };
 
 use crate::{
     debugging::{show_window, InspectorWindowsAppExt},
-    plugins::{network::LocalPlayerId, player::Player},
+    plugins::player::{LocalPLayer, Player, INTERACTION_DISTANCE_SQUARED},
+    utils::squared_distance,
     GameState, InspectorWindows,
 };
 
 use super::logic::{
-    Inventory, Item, ItemBundle, ItemEvent, ItemEventKind, ItemStack, ItemsLayout, Workbench,
+    break_worn_items, CustomName, Durability, Equipment, InstanceData, Inventory, Item, ItemBundle,
+    ItemDefinitions, ItemEvent, ItemEventKind, ItemEventResponse, ItemId, ItemInstance, ItemStack,
+    SortInventory, SortMode, TransferItem, Workbench,
 };
 
 pub struct WindowSystemsPlugin;
@@ -35,31 +46,53 @@ pub struct WindowSystemsPlugin;
 impl Plugin for WindowSystemsPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.init_resource::<AddItemWindow>()
-            .add_event::<CraftMessage>()
             // .add_systems(Startup, spawn_test_workbench)
             .register_window::<AddItemWindow>()
             .register_window::<InventoryWindow>()
-            .add_systems(Update, craft.run_if(in_state(GameState::Game)))
             .add_systems(
                 Update,
-                (add_item_window, add_item_event.run_if(has_authority))
+                (
+                    add_item_window,
+                    item_event_response,
+                    add_item_event.run_if(has_authority),
+                    transfer_item_event.run_if(has_authority),
+                    sort_inventory_event.run_if(has_authority),
+                )
                     .run_if(in_state(GameState::Game)),
             )
             .add_systems(
-                Update,
-                (
-                    handle_workbench_window,
-                    handle_inventory_window,
-                    handle_enchantment_window,
-                ),
-            );
+                PostUpdate,
+                (break_worn_items, remove_empty_stacks)
+                    .chain()
+                    .run_if(has_authority)
+                    .before(ServerSet::Send),
+            )
+            .add_systems(Update, handle_inventory_window);
     }
 }
 
 #[derive(AssetCollection, Resource)]
 pub struct WorkbenchesCollection {
-    #[asset(path = "workbenches", collection(typed))]
-    workbenches: Vec<Handle<Workbench>>,
+    #[asset(path = "workbenches", collection(typed, mapped))]
+    workbenches: bevy::utils::HashMap<String, Handle<Workbench>>,
+}
+
+impl WorkbenchesCollection {
+    /// Workbench with the asset path, e.g. `workbenches/classical.workbench.ron`.
+    pub fn get(&self, path: &str) -> Option<&Handle<Workbench>> {
+        self.workbenches.get(path)
+    }
+
+    /// Asset paths and handles of all workbenches, sorted by the path.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Handle<Workbench>)> {
+        let mut workbenches = self
+            .workbenches
+            .iter()
+            .map(|(path, handle)| (path.as_str(), handle))
+            .collect::<Vec<_>>();
+        workbenches.sort_unstable_by_key(|(path, _)| *path);
+        workbenches.into_iter()
+    }
 }
 
 #[derive(AssetCollection, Resource)]
@@ -78,6 +111,7 @@ pub struct ItemsCollection {
 struct AddItemWindow {
     stack: ItemStack,
     selected_item: usize,
+    last_response: Option<String>,
 }
 
 fn add_item_window(
@@ -111,18 +145,28 @@ fn add_item_window(
             ui,
             &type_registry.read(),
         );
-        if ui.button("Add").clicked() {
-            add_item_events.send(ItemEvent {
-                kind: ItemEventKind::Add,
-                inventory: None,
-                item: ItemBundle {
-                    item: items_asset
-                        .get(&items.items[add_item_window.selected_item])
-                        .unwrap()
-                        .clone(),
-                    stack: add_item_window.stack.clone(),
-                },
-            });
+        ui.horizontal(|ui| {
+            for (label, kind) in [
+                ("Add", ItemEventKind::Add),
+                ("Remove", ItemEventKind::Remove),
+            ] {
+                if ui.button(label).clicked() {
+                    add_item_events.send(ItemEvent {
+                        kind,
+                        inventory: None,
+                        item: ItemBundle {
+                            item: items_asset
+                                .get(&items.items[add_item_window.selected_item])
+                                .unwrap()
+                                .clone(),
+                            stack: add_item_window.stack.clone(),
+                        },
+                    });
+                }
+            }
+        });
+        if let Some(response) = &add_item_window.last_response {
+            ui.label(response);
         }
         if ui.button("Clear Inventory").clicked() {
             // if let Ok(mut inventory) = player_query.get_single_mut() {
@@ -134,72 +178,262 @@ fn add_item_window(
     });
 }
 
+/// How a player can access an inventory.
+#[derive(PartialEq)]
+enum InventoryAccess {
+    Own,
+    /// Inventory of a nearby non-player entity, e.g. a chest.
+    Container,
+    OtherPlayer,
+    Denied,
+}
+
+fn inventory_access(
+    inventories: &Query<(&mut Inventory, &GlobalTransform, Has<Player>)>,
+    player_entity: Entity,
+    target: Entity,
+) -> InventoryAccess {
+    if player_entity == target {
+        return InventoryAccess::Own;
+    }
+    let Ok([(_, player_transform, _), (_, target_transform, is_player)]) =
+        inventories.get_many([player_entity, target])
+    else {
+        return InventoryAccess::Denied;
+    };
+
+    if squared_distance(
+        player_transform.translation(),
+        target_transform.translation(),
+    ) > INTERACTION_DISTANCE_SQUARED
+    {
+        InventoryAccess::Denied
+    } else if is_player {
+        InventoryAccess::OtherPlayer
+    } else {
+        InventoryAccess::Container
+    }
+}
+
 fn add_item_event(
     mut commands: Commands,
     mut add_item_events: EventReader<FromClient<ItemEvent>>,
-    mut player_query: Query<(&mut Inventory, &Player)>,
-    mut items_query: Query<(&mut Item, &mut ItemStack)>,
+    mut responses: EventWriter<ToClients<ItemEventResponse>>,
+    players: Query<(Entity, &Player)>,
+    mut inventories: Query<(&mut Inventory, &GlobalTransform, Has<Player>)>,
+    mut items_query: Query<(&ItemId, &mut ItemStack, InstanceData)>,
+    definitions: ItemDefinitions,
 ) {
     for FromClient { client_id, event } in add_item_events.read() {
-        for (mut inventory, player) in player_query.iter_mut() {
-            if *client_id == player.0 {
-                match event.kind {
-                    ItemEventKind::Add => inventory.add_combine(
+        let ItemBundle { item, stack } = &event.item;
+        // Clients only choose the item, its definition is the server's one
+        let definition = definitions.get(&item.id);
+        let mut response = ItemEventResponse {
+            kind: event.kind,
+            inventory: None,
+            item: ItemBundle {
+                item: item.clone(),
+                stack: ItemStack(0),
+            },
+            success: false,
+        };
+
+        let target = players
+            .iter()
+            .find(|(_, player)| player.0 == *client_id)
+            .and_then(|(player_entity, _)| {
+                let target = event.inventory.unwrap_or(player_entity);
+                matches!(
+                    inventory_access(&inventories, player_entity, target),
+                    InventoryAccess::Own | InventoryAccess::Container
+                )
+                .then_some(target)
+            });
+
+        if definition.is_none() {
+            warn!("{client_id:?} sent an unknown item {:?}", item.id);
+        } else if let Some((target, Ok((mut inventory, _, _)))) =
+            target.map(|target| (target, inventories.get_mut(target)))
+        {
+            let amount = match event.kind {
+                ItemEventKind::Add => {
+                    let leftovers = inventory.add_combine(
                         &mut commands,
                         &mut items_query,
-                        vec![event.item.as_tuple()],
-                    ),
-                    ItemEventKind::Remove => warn!("unimplemented"),
+                        definition.into_iter().map(|item| (item, stack)).collect(),
+                    );
+                    stack.0 - leftovers.iter().map(|l| l.stack.0).sum::<u8>()
                 }
-            }
+                ItemEventKind::Remove => inventory.remove_item(&mut items_query, &item.id, stack.0),
+            };
+
+            response.inventory = Some(target);
+            response.item.stack = ItemStack(amount);
+            response.success = amount == stack.0;
+        } else {
+            warn!(
+                "{client_id:?} has no access to the inventory {:?}",
+                event.inventory
+            );
         }
+
+        responses.send(ToClients {
+            mode: SendMode::Direct(*client_id),
+            event: response,
+        });
     }
 }
 
-#[derive(Event)]
-pub struct CraftMessage {
-    pub input: ItemsLayout,
-    pub output: ItemsLayout,
+fn item_event_response(
+    mut responses: EventReader<ItemEventResponse>,
+    mut add_item_window: ResMut<AddItemWindow>,
+) {
+    for response in responses.read() {
+        let ItemEventResponse {
+            kind,
+            item: ItemBundle { item, stack },
+            success,
+            ..
+        } = response;
+        add_item_window.last_response = Some(match success {
+            true => format!("{kind:?}: {} x{}", item.name, stack.0),
+            false => format!("{kind:?} failed, moved only: {} x{}", item.name, stack.0),
+        });
+    }
 }
 
-fn craft(
+/// Sorts the inventory of the player who sent the event.
+fn sort_inventory_event(
+    mut sort_events: EventReader<FromClient<SortInventory>>,
+    mut players: Query<(&Player, &mut Inventory)>,
+    mut items_query: Query<(&ItemId, &mut ItemStack, InstanceData)>,
+    definitions: ItemDefinitions,
+) {
+    for FromClient { client_id, event } in sort_events.read() {
+        let Some((_, mut inventory)) = players
+            .iter_mut()
+            .find(|(player, _)| player.0 == *client_id)
+        else {
+            warn!("{client_id:?} tried to sort an inventory without a player");
+            continue;
+        };
+        inventory.sort(&mut items_query, &definitions, event.mode);
+    }
+}
+
+fn transfer_item_event(
     mut commands: Commands,
-    mut event_message: EventReader<CraftMessage>,
-    mut player_query: Query<&mut Inventory, With<Player>>,
-    workbenches: Res<WorkbenchesCollection>,
-    item_assets: Res<Assets<Item>>,
-    workbench_assets: Res<Assets<Workbench>>,
-    mut items_query: Query<(&mut Item, &mut ItemStack)>,
+    mut transfer_events: EventReader<FromClient<TransferItem>>,
+    players: Query<(Entity, &Player)>,
+    mut inventories: Query<(&mut Inventory, &GlobalTransform, Has<Player>)>,
+    mut items_query: Query<(&ItemId, &mut ItemStack, InstanceData)>,
+    definitions: ItemDefinitions,
 ) {
-    for event in event_message.read() {
-        let CraftMessage { input, .. } = event;
-        let mut player_inventory = player_query.get_single_mut().unwrap();
+    for FromClient { client_id, event } in transfer_events.read() {
+        let TransferItem {
+            from,
+            to,
+            slot,
+            amount,
+        } = *event;
 
-        if let Some(layout) =
-            player_inventory.take_satisfying_layout(&items_query.to_readonly(), input)
-        {
-            for (inp, out_entity) in input.0.iter().zip(layout.into_iter()) {
-                if let Ok((_, mut out_stack)) = items_query.get_mut(out_entity) {
-                    out_stack.0 -= inp.stack.0;
-                    player_inventory.add_single(out_entity);
-                }
+        let Some((player_entity, _)) = players.iter().find(|(_, player)| player.0 == *client_id)
+        else {
+            warn!("{client_id:?} tried to transfer items without a player");
+            continue;
+        };
+
+        // Items can be taken only from the own inventory or from a nearby container,
+        // but can be given to any nearby inventory.
+        let can_take = matches!(
+            inventory_access(&inventories, player_entity, from),
+            InventoryAccess::Own | InventoryAccess::Container
+        );
+        let can_give = inventory_access(&inventories, player_entity, to) != InventoryAccess::Denied;
+        if !can_take || !can_give {
+            warn!("{client_id:?} has no access to the inventories");
+            continue;
+        }
+
+        let Ok([(from_inventory, _, _), (mut to_inventory, _, _)]) =
+            inventories.get_many_mut([from, to])
+        else {
+            warn!("{client_id:?} tried to transfer items between invalid inventories");
+            continue;
+        };
+
+        let Some(item_entity) = from_inventory.get(slot) else {
+            warn!("{client_id:?} tried to transfer items from an empty slot");
+            continue;
+        };
+        let Ok((id, mut stack, data)) = items_query.get_mut(item_entity) else {
+            continue;
+        };
+        let instance = data.to_instance();
+        let Some(item) = definitions.get(id) else {
+            warn!("{client_id:?} tried to transfer an unknown item {id:?}");
+            continue;
+        };
+        if amount == 0 || amount > stack.0 {
+            warn!(
+                "{client_id:?} tried to transfer {amount} items out of {}",
+                stack.0
+            );
+            continue;
+        }
+
+        stack.0 -= amount;
+        // Instance data moves with the items, e.g. durability and custom names
+        let leftover =
+            to_inventory.add_instance(&mut commands, &mut items_query, item, amount, &instance);
+
+        // Items that didn't fit are returned back to the source stack
+        if let Ok((_, mut stack, _)) = items_query.get_mut(item_entity) {
+            stack.0 += leftover;
+        }
+    }
+}
+
+/// Despawns item entities whose stack became empty and clears the inventory
+/// and equipment slots they occupied, so an item inside of an [`Inventory`]
+/// or [`Equipment`] always has at least one item in its stack.
+///
+/// Despawns and inventory changes are replicated, so clients stay in sync.
+fn remove_empty_stacks(
+    mut commands: Commands,
+    stacks_query: Query<(Entity, &ItemStack), Changed<ItemStack>>,
+    mut inventories: Query<&mut Inventory>,
+    mut equipments: Query<&mut Equipment>,
+) {
+    let empty = stacks_query
+        .iter()
+        .filter_map(|(entity, stack)| (stack.0 == 0).then_some(entity))
+        .collect::<Vec<_>>();
+
+    if empty.is_empty() {
+        return;
+    }
+
+    for mut inventory in inventories.iter_mut() {
+        for entity in empty.iter() {
+            if inventory.contains(*entity) {
+                inventory.take_linear(*entity);
             }
+        }
+    }
 
-            for workbench in workbenches
-                .workbenches
-                .iter()
-                .filter_map(|h| workbench_assets.get(h))
-            {
-                if let Some(layout) = workbench.craft(&item_assets, input) {
-                    player_inventory.add_combine(&mut commands, &mut items_query, layout);
-                } else {
-                    error!("Crafting failed on stage: 2")
-                }
+    for mut equipment in equipments.iter_mut() {
+        for entity in empty.iter() {
+            if equipment.items().any(|(_, e)| e == *entity) {
+                equipment.remove(*entity);
             }
-        } else {
-            error!("Crafting failed on stage: 1")
         }
     }
+
+    // Dropped items have a pickup sensor as a child
+    for entity in empty {
+        commands.entity(entity).despawn_recursive();
+    }
 }
 
 pub fn show_item(item_bundle: (&Item, &ItemStack), ui: &mut Ui, enabled: bool) {
@@ -217,84 +451,84 @@ pub fn show_item(item_bundle: (&Item, &ItemStack), ui: &mut Ui, enabled: bool) {
     });
 }
 
-fn show_craft_layout(layout: &ItemsLayout, ui: &mut Ui, enabled: bool) {
-    layout.get().iter().for_each(|item| {
-        let ItemBundle { item, stack } = item;
-        show_item((item, stack), ui, enabled);
+/// Text field for filtering the shown items with [`Item::matches_search`].
+pub fn show_search(search: &mut String, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        ui.label("Search:");
+        ui.text_edit_singleline(search);
+        if ui.button("✖").clicked() {
+            search.clear();
+        }
     });
 }
 
-fn handle_enchantment_window(
-    _contexts: EguiContexts,
-    _window_context: Res<InspectorWindows>,
-    _player_query: Query<&Inventory, With<Player>>,
-) {
+/// Buttons sending [`SortInventory`] for the local player's inventory.
+fn show_sort_buttons(sort_events: &mut EventWriter<SortInventory>, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        ui.label("Sort:");
+        for mode in SortMode::ALL {
+            if ui.button(format!("{mode:?}")).clicked() {
+                sort_events.send(SortInventory { mode });
+            }
+        }
+    });
 }
 
-fn handle_workbench_window(
-    _contexts: EguiContexts,
-    _workbench_window_state: ResMut<InspectorWindows>,
-    _craft_event_message: EventWriter<CraftMessage>,
-    _player_query: Query<(&Inventory, &Player)>,
-    _items_query: Query<(&Item, &ItemStack)>,
-    _local_player: Option<Res<LocalPlayerId>>,
-) {
-    // egui::Window::new(crafts_map.name())
-    //     .open(&mut workbench_window_state.workbench_window)
-    //     .resizable(true)
-    //     .show(contexts.ctx_mut(), |ui| {
-    //         if let Some(local_player) = local_player {
-    //             for (inventory, player) in player_query.iter() {
-    //                 if player.0 == local_player.0 {
-    //                     for (input, output) in crafts_map.map().iter() {
-    //                         let enabled =
-    //                             inventory.search_satisfying(&items_query, input).is_some();
-
-    //                         ui.horizontal(|ui| {
-    //                             show_craft_layout(input, ui, enabled);
-
-    //                             ui.separator();
-
-    //                             show_craft_layout(output, ui, enabled);
-
-    //                             if ui
-    //                                 .add_enabled(enabled, egui::Button::new("Craft"))
-    //                                 .clicked()
-    //                             {
-    //                                 craft_event_message.send(CraftMessage {
-    //                                     input: input.clone(),
-    //                                     output: output.clone(),
-    //                                     _marker: PhantomData,
-    //                                 })
-    //                             }
-    //                         });
-    //                     }
-    //                 }
-    //             }
-    //         } else {
-    //             ui.label("No players");
-    //         }
-    //     });
+/// Shows the per-instance data of an item next to [`show_item`].
+pub fn show_instance(instance: &ItemInstance, ui: &mut Ui) {
+    if let Some(CustomName(name)) = &instance.custom_name {
+        ui.label(format!("\"{name}\""));
+    }
+    if let Some(Durability { current, max }) = &instance.durability {
+        ui.label(format!("{current}/{max}"))
+            .on_hover_text("Durability");
+    }
+    if let Some(metadata) = &instance.metadata {
+        let text = metadata
+            .iter()
+            .map(|(key, value)| format!("{key}: {value}"))
+            .collect::<Vec<_>>();
+        ui.label("…").on_hover_text(text.join("\n"));
+    }
 }
 
 #[derive(TypePath)]
 enum InventoryWindow {}
 
+/// Inventories of all players, only items matching the search are shown.
+/// The local player's inventory can be sorted.
 fn handle_inventory_window(
     mut contexts: EguiContexts,
     mut inspector_windows: ResMut<InspectorWindows>,
-    player_query: Query<(&Inventory, &Player)>,
-    input_items_query: Query<(&Item, &ItemStack)>,
+    mut search: Local<String>,
+    player_query: Query<(&Inventory, &Player, Has<LocalPLayer>)>,
+    input_items_query: Query<(&ItemId, &ItemStack, InstanceData)>,
+    definitions: ItemDefinitions,
+    mut sort_events: EventWriter<SortInventory>,
 ) {
     show_window::<InventoryWindow, _>(inspector_windows.as_mut(), contexts.ctx_mut(), |ui| {
+        show_search(&mut search, ui);
         ui.horizontal(|ui| {
-            for (inventory, player) in player_query.iter() {
+            for (inventory, player, is_local) in player_query.iter() {
                 ui.vertical(|ui| {
                     ui.label(format!("{:?}", player.0));
-                    for entity in inventory.map.iter().filter_map(|x| *x) {
-                        if let Ok(item) = input_items_query.get(entity) {
-                            show_item(item, ui, true);
-                        }
+                    if is_local {
+                        show_sort_buttons(&mut sort_events, ui);
+                    }
+                    for entity in inventory.map.iter().flatten() {
+                        let Ok((id, stack, data)) = input_items_query.get(*entity) else {
+                            continue;
+                        };
+                        let Some(item) = definitions
+                            .get(id)
+                            .filter(|item| item.matches_search(&search))
+                        else {
+                            continue;
+                        };
+                        ui.horizontal(|ui| {
+                            show_item((item, stack), ui, true);
+                            show_instance(&data.to_instance(), ui);
+                        });
                     }
                 });
                 ui.separator();
@@ -1,8 +1,38 @@
 use bevy::{
+    app::{Plugin, Update},
+    asset::{Assets, Handle},
     core::Name,
-    ecs::{bundle::Bundle, component::Component},
+    ecs::{
+        bundle::Bundle,
+        component::Component,
+        entity::Entity,
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Commands, Query, Res},
+    },
+    math::Vec3,
     reflect::{std_traits::ReflectDefault, Reflect},
+    transform::components::GlobalTransform,
 };
+use bevy_replicon::core::common_conditions::has_authority;
+
+use crate::GameState;
+
+use super::crafting::logic::{DroppedItem, Item, LootContext, LootSource, LootTable};
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.register_type::<Health>()
+            .register_type::<Enemy>()
+            .add_systems(
+                Update,
+                drop_enemy_loot
+                    .run_if(has_authority)
+                    .run_if(in_state(GameState::Game)),
+            );
+    }
+}
 
 #[derive(Debug, Component, Reflect)]
 #[reflect(Default)]
@@ -14,13 +44,149 @@ impl Default for Health {
     }
 }
 
+impl Health {
+    pub fn is_dead(&self) -> bool {
+        self.0 == 0
+    }
+}
+
 #[derive(Debug, Component, Reflect, Default)]
 #[reflect(Default)]
 pub struct Enemy;
 
+/// Items dropped by the enemy when it dies, rolled from the `table` with the `seed`,
+/// e.g. a [`LootTable::position_seed`]. Only exists on the server.
+#[derive(Debug, Component, Clone)]
+pub struct EnemyLoot {
+    pub table: Handle<LootTable>,
+    pub seed: u64,
+    pub level: u8,
+}
+
 #[derive(Bundle)]
 pub struct EnemyBundle {
     pub name: Name,
     pub health: Health,
     pub enemy: Enemy,
 }
+
+/// Drops the loot of dead enemies as [`DroppedItem`]s around them, only once per enemy.
+/// The loot waits on the enemy until its table is loaded.
+fn drop_enemy_loot(
+    mut commands: Commands,
+    enemies: Query<(Entity, &Health, &EnemyLoot, &GlobalTransform)>,
+    table_assets: Res<Assets<LootTable>>,
+    item_assets: Res<Assets<Item>>,
+) {
+    for (entity, health, loot, transform) in enemies.iter() {
+        if !health.is_dead() {
+            continue;
+        }
+        let Some(table) = table_assets.get(&loot.table) else {
+            continue;
+        };
+        let context = LootContext {
+            source: LootSource::Enemy,
+            level: loot.level,
+        };
+        for (i, (item, stack)) in table
+            .roll_items(loot.seed, &context, &table_assets, &item_assets)
+            .into_iter()
+            .enumerate()
+        {
+            let offset = Vec3::new(i as f32 * 0.6, 1.0, 0.0);
+            DroppedItem::spawn(
+                &mut commands,
+                &item.id,
+                stack,
+                transform.translation() + offset,
+            );
+        }
+        commands.entity(entity).remove::<EnemyLoot>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::{AssetId, Assets},
+        ecs::{
+            query::With,
+            system::{Query, RunSystemOnce},
+            world::World,
+        },
+        transform::components::GlobalTransform,
+    };
+
+    use super::{drop_enemy_loot, EnemyLoot, Health};
+    use crate::{
+        asset_ref::AssetRef,
+        plugins::crafting::logic::{
+            DroppedItem, Item, ItemId, ItemStack, Loot, LootEntry, LootTable, Quantity,
+        },
+    };
+
+    #[test]
+    fn dead_enemy_drops_loot_once_table_is_loaded() {
+        let mut world = World::new();
+        let mut item_assets = Assets::<Item>::default();
+        let bone = item_assets.add(Item {
+            id: ItemId("bone".to_string()),
+            ..Default::default()
+        });
+        let mut table_assets = Assets::<LootTable>::default();
+        let table = table_assets.reserve_handle();
+        world.insert_resource(item_assets);
+        world.insert_resource(table_assets);
+
+        let enemy = world
+            .spawn((
+                Health::default(),
+                EnemyLoot {
+                    table: table.clone(),
+                    seed: 3,
+                    level: 1,
+                },
+                GlobalTransform::default(),
+            ))
+            .id();
+        let dropped = |world: &mut World| {
+            world.run_system_once(|items: Query<(&ItemId, &ItemStack), With<DroppedItem>>| {
+                items
+                    .iter()
+                    .map(|(id, stack)| (id.0.clone(), stack.0))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        world.run_system_once(drop_enemy_loot);
+        assert!(dropped(&mut world).is_empty());
+
+        world.get_mut::<Health>(enemy).unwrap().0 = 0;
+        world.run_system_once(drop_enemy_loot);
+        assert!(dropped(&mut world).is_empty());
+        assert!(world.get::<EnemyLoot>(enemy).is_some());
+
+        world.resource_mut::<Assets<LootTable>>().insert(
+            AssetId::from(&table),
+            LootTable {
+                rolls: Quantity { min: 2, max: 2 },
+                entries: vec![LootEntry {
+                    weight: 1,
+                    loot: Loot::Item {
+                        item: AssetRef::Handle(bone),
+                        count: Quantity { min: 3, max: 3 },
+                    },
+                    conditions: Vec::new(),
+                }],
+            },
+        );
+        world.run_system_once(drop_enemy_loot);
+        world.run_system_once(drop_enemy_loot);
+        assert_eq!(
+            dropped(&mut world),
+            vec![("bone".to_string(), 3), ("bone".to_string(), 3)]
+        );
+        assert!(world.get::<EnemyLoot>(enemy).is_none());
+    }
+}
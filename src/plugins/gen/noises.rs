@@ -28,6 +28,12 @@ pub struct NoiseConfig {
     offset: Vec2,
 }
 
+impl NoiseConfig {
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+}
+
 impl Default for NoiseConfig {
     fn default() -> Self {
         Self {
@@ -0,0 +1,128 @@
+use bevy::{
+    app::{Plugin, Update},
+    ecs::{
+        component::Component,
+        query::With,
+        schedule::{common_conditions::in_state, IntoSystemConfigs, OnEnter},
+        system::{Commands, Query},
+    },
+    hierarchy::BuildChildren,
+    render::color::Color,
+    text::{Text, TextStyle},
+    ui::{
+        node_bundles::{NodeBundle, TextBundle},
+        AlignItems, BackgroundColor, BorderColor, JustifyContent, PositionType, Style, UiRect, Val,
+    },
+};
+
+use crate::GameState;
+
+use super::{
+    crafting::logic::{Inventory, ItemDefinitions, ItemId, ItemStack},
+    player::{LocalPLayer, SelectedSlot, HOTBAR_SLOTS},
+};
+
+pub struct HotbarPlugin;
+
+impl Plugin for HotbarPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(OnEnter(GameState::Game), hotbar_setup)
+            .add_systems(Update, update_hotbar.run_if(in_state(GameState::Game)));
+    }
+}
+
+/// Node of the hotbar slot with the index.
+#[derive(Component)]
+struct HotbarSlot(usize);
+
+/// Text with the item in the hotbar slot with the index.
+#[derive(Component)]
+struct HotbarLabel(usize);
+
+const SELECTED_COLOR: Color = Color::LIME_GREEN;
+const SLOT_COLOR: Color = Color::GRAY;
+
+/// Row of slots at the bottom of the screen, below the crosshair.
+fn hotbar_setup(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                column_gap: Val::Px(4.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for slot in 0..HOTBAR_SLOTS {
+                parent
+                    .spawn((
+                        HotbarSlot(slot),
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Px(64.0),
+                                height: Val::Px(64.0),
+                                border: UiRect::all(Val::Px(2.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..Default::default()
+                            },
+                            border_color: BorderColor(SLOT_COLOR),
+                            background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.5)),
+                            ..Default::default()
+                        },
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            HotbarLabel(slot),
+                            TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    font_size: 12.0,
+                                    color: Color::WHITE,
+                                    ..Default::default()
+                                },
+                            ),
+                        ));
+                    });
+            }
+        });
+}
+
+/// Shows the items in the first slots of the local player's inventory and highlights
+/// the [`SelectedSlot`].
+fn update_hotbar(
+    player_query: Query<(&Inventory, &SelectedSlot), With<LocalPLayer>>,
+    items_query: Query<(&ItemId, &ItemStack)>,
+    definitions: ItemDefinitions,
+    mut slots: Query<(&HotbarSlot, &mut BorderColor)>,
+    mut labels: Query<(&HotbarLabel, &mut Text)>,
+) {
+    let Ok((inventory, selected)) = player_query.get_single() else {
+        return;
+    };
+    for (HotbarSlot(slot), mut border) in slots.iter_mut() {
+        let color = if *slot == selected.0 {
+            SELECTED_COLOR
+        } else {
+            SLOT_COLOR
+        };
+        if border.0 != color {
+            border.0 = color;
+        }
+    }
+    for (HotbarLabel(slot), mut text) in labels.iter_mut() {
+        let label = inventory
+            .get(*slot)
+            .and_then(|entity| items_query.get(entity).ok())
+            .and_then(|(id, stack)| Some((definitions.get(id)?, stack)))
+            .map(|(item, stack)| format!("{}\nx{}", item.name, stack.0))
+            .unwrap_or_default();
+        if text.sections[0].value != label {
+            text.sections[0].value = label;
+        }
+    }
+}
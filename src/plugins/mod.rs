@@ -6,5 +6,6 @@ pub mod cursor;
 pub mod enemy;
 pub mod environment;
 pub mod gen;
+pub mod hotbar;
 pub mod network;
 pub mod player;
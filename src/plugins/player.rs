@@ -1,4 +1,4 @@
-use bevy::{ecs::query::Has, prelude::*};
+use bevy::{ecs::query::Has, input::mouse::MouseWheel, prelude::*};
 use bevy_asset_loader::asset_collection::AssetCollection;
 use bevy_replicon::{
     client::ClientSet,
@@ -22,7 +22,14 @@ use serde::{Deserialize, Serialize};
 
 use crate::plugins::gen::chunking::ChunksRenderer;
 
-use super::{camera::fly_view, crafting::logic::Inventory, network::LocalPlayerId};
+use super::{
+    camera::fly_view,
+    crafting::logic::{
+        Equipment, Inventory, ItemDefinitions, ItemEnchantments, ItemId, ItemProperties,
+        UnlockedRecipes,
+    },
+    network::LocalPlayerId,
+};
 
 pub struct PlayerPlugin;
 
@@ -30,10 +37,15 @@ impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.replicate::<PlayerColor>()
             .replicate::<Player>()
+            .replicate::<PlayerProperties>()
+            .replicate::<SelectedSlot>()
+            .register_type::<PlayerProperties>()
+            .register_type::<SelectedSlot>()
             .register_type::<JumpImpulse>()
             .register_type::<SpringSettings>()
             .add_client_event::<MovePlayer>(ChannelKind::Ordered)
             .add_client_event::<RotatePlayer>(ChannelKind::Ordered)
+            .add_client_event::<SelectSlot>(ChannelKind::Ordered)
             .add_systems(
                 PreUpdate,
                 (player_init_system, init_local_player).after(ClientSet::Receive),
@@ -41,7 +53,7 @@ impl Plugin for PlayerPlugin {
             .add_systems(
                 Update,
                 (
-                    rotate_player.run_if(has_authority),
+                    (rotate_player, select_slot, update_player_properties).run_if(has_authority),
                     input_system.run_if(not(fly_view)),
                     (
                         update_grounded,
@@ -63,6 +75,9 @@ impl Plugin for PlayerPlugin {
     }
 }
 
+/// Squared distance within which a player can interact with other entities.
+pub const INTERACTION_DISTANCE_SQUARED: f32 = 500.0;
+
 #[derive(AssetCollection, Resource)]
 pub struct PlayerCollection {}
 
@@ -85,6 +100,33 @@ pub enum MovePlayer {
 #[derive(Debug, Default, Deserialize, Event, Serialize)]
 pub struct RotatePlayer(pub Quat);
 
+/// Number of the first [`Inventory`] slots shown in the hotbar.
+pub const HOTBAR_SLOTS: usize = 9;
+
+/// Hotbar slot selected by the player, the item in it is the one held in hand.
+#[derive(
+    Component, Default, Clone, Copy, PartialEq, Eq, Debug, Reflect, Serialize, Deserialize,
+)]
+#[reflect(Default)]
+pub struct SelectedSlot(pub usize);
+
+impl SelectedSlot {
+    /// Slot `delta` slots away, wrapping around the hotbar.
+    pub fn scrolled(self, delta: i32) -> Self {
+        Self((self.0 as i32 + delta).rem_euclid(HOTBAR_SLOTS as i32) as usize)
+    }
+
+    /// Item entity in the selected slot of the `inventory`.
+    pub fn item(self, inventory: &Inventory) -> Option<Entity> {
+        inventory.get(self.0)
+    }
+}
+
+/// Request to select the hotbar slot, the server ignores slots outside the hotbar
+/// and replicates the choice as [`SelectedSlot`].
+#[derive(Debug, Default, Deserialize, Event, Serialize)]
+pub struct SelectSlot(pub usize);
+
 #[derive(Component, Deserialize, Serialize, Default)]
 pub struct PlayerColor(pub Color);
 
@@ -239,6 +281,10 @@ pub struct PlayerBundle {
     pub transform: Transform,
     pub color: PlayerColor,
     pub inventory: Inventory,
+    pub unlocked_recipes: UnlockedRecipes,
+    pub properties: PlayerProperties,
+    pub equipment: Equipment,
+    pub selected_slot: SelectedSlot,
 }
 
 impl PlayerBundle {
@@ -249,12 +295,52 @@ impl PlayerBundle {
             transform: Transform::from_xyz(0.0, 40.0, 0.0),
             color: PlayerColor(color),
             inventory: Inventory::default(),
+            unlocked_recipes: UnlockedRecipes::default(),
+            properties: PlayerProperties::default(),
+            equipment: Equipment::default(),
+            selected_slot: SelectedSlot::default(),
         }
     }
 }
 
-#[derive(Component, Default)]
-pub struct PlayerProperties {}
+/// Stats of the player, the sum of the [`ItemProperties`] and [`ItemEnchantments`]
+/// of the items in the [`Equipment`].
+#[derive(Component, Default, Clone, PartialEq, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Default)]
+pub struct PlayerProperties {
+    pub stats: ItemProperties,
+}
+
+impl PlayerProperties {
+    /// Multiplier of the [`MovementAcceleration`], never negative.
+    pub fn speed_multiplier(&self) -> f32 {
+        (1.0 + self.stats.speed).max(0.0)
+    }
+}
+
+/// Recomputes the [`PlayerProperties`] from the equipped items, so the stats
+/// and enchantments of unequipped items are no longer applied.
+/// Definitions are resolved every time, so reloaded items apply immediately.
+fn update_player_properties(
+    mut players: Query<(&Equipment, &mut PlayerProperties)>,
+    items: Query<(&ItemId, Option<&ItemEnchantments>)>,
+    definitions: ItemDefinitions,
+) {
+    for (equipment, mut properties) in players.iter_mut() {
+        let mut stats = ItemProperties::default();
+        for (id, enchantments) in items.iter_many(equipment.items().map(|(_, entity)| entity)) {
+            let Some(item_properties) = definitions.get(id).and_then(|item| item.kind.properties())
+            else {
+                continue;
+            };
+            stats.combine(item_properties);
+            if let Some(enchantments) = enchantments {
+                stats.combine(&enchantments.stats());
+            }
+        }
+        properties.set_if_neq(PlayerProperties { stats });
+    }
+}
 
 fn player_init_system(
     mut commands: Commands,
@@ -315,12 +401,50 @@ fn rotate_player(
     }
 }
 
+/// Selects the hotbar slot of the player, slots outside the hotbar or the inventory are ignored.
+fn select_slot(
+    mut select_events: EventReader<FromClient<SelectSlot>>,
+    mut players: Query<(&Player, &Inventory, &mut SelectedSlot)>,
+) {
+    for FromClient { client_id, event } in select_events.read() {
+        let Some((_, inventory, mut selected)) = players
+            .iter_mut()
+            .find(|(player, ..)| player.0 == *client_id)
+        else {
+            continue;
+        };
+        if event.0 < HOTBAR_SLOTS.min(inventory.capacity()) {
+            selected.set_if_neq(SelectedSlot(event.0));
+        } else {
+            warn!(
+                "{client_id:?} selected a slot outside the hotbar: {}",
+                event.0
+            );
+        }
+    }
+}
+
+const HOTBAR_KEYS: [KeyCode; HOTBAR_SLOTS] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
 fn input_system(
     mut move_events: EventWriter<MovePlayer>,
+    mut select_events: EventWriter<SelectSlot>,
+    mut wheel_events: EventReader<MouseWheel>,
     input: Res<ButtonInput<KeyCode>>,
-    player: Query<&Transform, With<LocalPLayer>>,
+    player: Query<(&Transform, &SelectedSlot), With<LocalPLayer>>,
+    mut pending_slot: Local<Option<SelectedSlot>>,
 ) {
-    let Ok(player_transform) = player.get_single() else {
+    let Ok((player_transform, selected)) = player.get_single() else {
         return;
     };
 
@@ -345,6 +469,28 @@ fn input_system(
     if input.just_pressed(KeyCode::Space) {
         move_events.send(MovePlayer::Jump);
     }
+
+    // Scrolling down selects the next slot, horizontal scrolling has zero `y`
+    let scroll = wheel_events
+        .read()
+        .filter(|wheel| wheel.y != 0.0)
+        .map(|wheel| -wheel.y.signum() as i32)
+        .sum::<i32>();
+    // Until the server replicates the requested slot, scrolling continues from it,
+    // so quick scrolls are not lost
+    if *pending_slot == Some(*selected) {
+        *pending_slot = None;
+    }
+    let current = pending_slot.unwrap_or(*selected);
+    let slot = HOTBAR_KEYS
+        .iter()
+        .position(|key| input.just_pressed(*key))
+        .map(SelectedSlot)
+        .unwrap_or_else(|| current.scrolled(scroll));
+    if slot != current {
+        select_events.send(SelectSlot(slot.0));
+        *pending_slot = Some(slot);
+    }
 }
 
 fn movement_system(
@@ -353,6 +499,7 @@ fn movement_system(
     // mut players: Query<(&Player, &mut LinearVelocity)>,
     mut controllers: Query<(
         &Player,
+        &PlayerProperties,
         &MovementAcceleration,
         &JumpImpulse,
         &mut LinearVelocity,
@@ -360,14 +507,21 @@ fn movement_system(
     )>,
 ) {
     for FromClient { client_id, event } in move_events.read() {
-        for (player, movement_acceleration, jump_impulse, mut linear_velocity, is_grounded) in
-            &mut controllers
+        for (
+            player,
+            properties,
+            movement_acceleration,
+            jump_impulse,
+            mut linear_velocity,
+            is_grounded,
+        ) in &mut controllers
         {
             if *client_id == player.0 {
                 match event {
                     MovePlayer::Move(direction) => {
-                        linear_velocity.0 +=
-                            *direction * movement_acceleration.0 * time.delta_seconds();
+                        let acceleration = movement_acceleration.0
+                            * properties.speed_multiplier().adjust_precision();
+                        linear_velocity.0 += *direction * acceleration * time.delta_seconds();
                     }
                     MovePlayer::Jump => {
                         if is_grounded {
@@ -524,3 +678,16 @@ fn kinematic_controller_collisions(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{SelectedSlot, HOTBAR_SLOTS};
+
+    #[test]
+    fn scrolling_wraps_around_the_hotbar() {
+        assert_eq!(SelectedSlot(0).scrolled(1), SelectedSlot(1));
+        assert_eq!(SelectedSlot(0).scrolled(-1), SelectedSlot(HOTBAR_SLOTS - 1));
+        assert_eq!(SelectedSlot(HOTBAR_SLOTS - 1).scrolled(2), SelectedSlot(1));
+        assert_eq!(SelectedSlot(3).scrolled(0), SelectedSlot(3));
+    }
+}